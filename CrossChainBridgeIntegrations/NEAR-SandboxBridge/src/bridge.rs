@@ -1,9 +1,12 @@
 use near_sdk::{env, AccountId, Balance, Promise};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 
 const BRIDGE_FEE_BPS: u32 = 30; // 0.3% bridge fee
 const MIN_TRANSFER: Balance = 1_000_000; // Minimum transfer amount
 const CONFIRMATION_BLOCKS: u64 = 30; // Number of blocks to wait for confirmation
+const MAX_QUERY_RESULTS: usize = 100; // Cap on records returned by history queries
+const REFUND_TIMEOUT: u64 = 24 * 3600 * 1_000_000_000; // 24 hours in nanoseconds
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BridgeConfig {
@@ -14,6 +17,10 @@ pub struct BridgeConfig {
     pub min_transfer: Balance,
     pub max_transfer: Balance,
     pub confirmation_blocks: u64,
+    // Derive tx hashes from the transfer and a call counter instead of the
+    // random seed, so sandbox and test runs are reproducible
+    #[serde(default)]
+    pub deterministic_tx_hashes: bool,
     pub protocol_config: ProtocolConfig,
     pub oracle_config: OracleConfig,
 }
@@ -49,10 +56,53 @@ pub enum TransactionStatus {
     Completed,
 }
 
+const EVM_CHAINS: [&str; 6] = ["ethereum", "aurora", "polygon", "arbitrum", "optimism", "bsc"];
+
+// Checks that `address` is well-formed for the destination chain: 0x-prefixed
+// 20-byte hex on EVM chains, a named `.near` account or 64-hex implicit
+// account on NEAR
+pub fn validate_destination(chain: &str, address: &str) -> Result<(), String> {
+    let chain = chain.to_lowercase();
+
+    if EVM_CHAINS.contains(&chain.as_str()) {
+        let valid = address
+            .strip_prefix("0x")
+            .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+        if !valid {
+            return Err(format!("Invalid {} address: {}", chain, address));
+        }
+        return Ok(());
+    }
+
+    if chain == "near" {
+        let implicit = address.len() == 64
+            && address.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+        let named = address
+            .strip_suffix(".near")
+            .is_some_and(|name| {
+                !name.is_empty()
+                    && name.split('.').all(|part| {
+                        !part.is_empty()
+                            && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+                    })
+            });
+        if !implicit && !named {
+            return Err(format!("Invalid near address: {}", address));
+        }
+        return Ok(());
+    }
+
+    Err(format!("Unsupported destination chain: {}", chain))
+}
+
 pub struct Bridge {
     config: BridgeConfig,
     transactions: Vec<BridgeTransaction>,
     total_volume: Balance,
+    completed_count: u64,
+    refund_timeout: u64,
+    fee_schedule: Vec<(Balance, u32)>, // (minimum amount, fee bps), ascending
+    tx_nonce: Cell<u64>, // hashes generated so far, for deterministic mode
     last_sync: u64,
 }
 
@@ -62,6 +112,10 @@ impl Bridge {
             config,
             transactions: Vec::new(),
             total_volume: 0,
+            completed_count: 0,
+            refund_timeout: REFUND_TIMEOUT,
+            fee_schedule: vec![(0, BRIDGE_FEE_BPS)],
+            tx_nonce: Cell::new(0),
             last_sync: env::block_timestamp(),
         }
     }
@@ -72,25 +126,9 @@ impl Bridge {
         receiver: AccountId,
         amount: Balance,
     ) -> Result<String, String> {
-        // Validate transfer
-        self.validate_transfer(&sender, amount)?;
-
-        // Calculate fees
-        let fee = self.calculate_fee(amount);
-        let net_amount = amount - fee;
-
-        // Create transaction record
-        let tx_hash = self.generate_tx_hash();
-        let transaction = BridgeTransaction {
-            tx_hash: tx_hash.clone(),
-            from_chain: self.config.source_chain.clone(),
-            to_chain: self.config.target_chain.clone(),
-            sender,
-            receiver,
-            amount: net_amount,
-            timestamp: env::block_timestamp(),
-            status: TransactionStatus::Pending,
-        };
+        // Validate transfer and create transaction record
+        let transaction = self.prepare_transfer(sender, receiver, amount)?;
+        let tx_hash = transaction.tx_hash.clone();
 
         // Lock tokens on source chain
         self.lock_tokens(&transaction)?;
@@ -102,6 +140,17 @@ impl Bridge {
         Ok(tx_hash)
     }
 
+    // Dry run of `transfer`: validates and prices the transfer and returns the
+    // record it would create, without locking tokens or recording anything
+    pub fn simulate_transfer(
+        &self,
+        sender: AccountId,
+        receiver: AccountId,
+        amount: Balance,
+    ) -> Result<BridgeTransaction, String> {
+        self.prepare_transfer(sender, receiver, amount)
+    }
+
     pub async fn confirm_transfer(&mut self, tx_hash: &str) -> Result<(), String> {
         let tx = self.transactions
             .iter_mut()
@@ -124,6 +173,60 @@ impl Bridge {
         self.release_tokens(tx)?;
 
         tx.status = TransactionStatus::Completed;
+        self.completed_count += 1;
+        Ok(())
+    }
+
+    // Tiers must start at 0, with rising thresholds and non-increasing fees
+    pub fn set_fee_schedule(&mut self, schedule: Vec<(Balance, u32)>) -> Result<(), String> {
+        match schedule.first() {
+            Some((0, _)) => {}
+            _ => return Err("Fee schedule must start at 0".to_string()),
+        }
+
+        for pair in schedule.windows(2) {
+            let ((lower, lower_bps), (upper, upper_bps)) = (pair[0], pair[1]);
+            if upper <= lower {
+                return Err("Fee thresholds must be strictly increasing".to_string());
+            }
+            if upper_bps > lower_bps {
+                return Err("Fees cannot rise for larger amounts".to_string());
+            }
+        }
+
+        if schedule.iter().any(|&(_, bps)| bps > 10_000) {
+            return Err("Fee cannot exceed 100%".to_string());
+        }
+
+        self.fee_schedule = schedule;
+        Ok(())
+    }
+
+    pub fn set_refund_timeout(&mut self, timeout: u64) {
+        self.refund_timeout = timeout;
+    }
+
+    // Returns the locked tokens of a transfer that was never confirmed
+    pub async fn refund_transfer(&mut self, tx_hash: &str) -> Result<(), String> {
+        let index = self.transactions
+            .iter()
+            .position(|t| t.tx_hash == tx_hash)
+            .ok_or("Transaction not found")?;
+        let tx = self.transactions[index].clone();
+
+        match tx.status {
+            TransactionStatus::Pending => {}
+            TransactionStatus::Completed => return Err("Transaction already completed".to_string()),
+            _ => return Err("Invalid transaction status".to_string()),
+        }
+
+        if env::block_timestamp().saturating_sub(tx.timestamp) < self.refund_timeout {
+            return Err("Refund timeout not reached".to_string());
+        }
+
+        self.unlock_tokens(&tx).await?;
+
+        self.transactions[index].status = TransactionStatus::Failed;
         Ok(())
     }
 
@@ -138,6 +241,32 @@ impl Bridge {
             .collect()
     }
 
+    // Most recent transfers sent or received by `account`, newest first
+    pub fn get_transactions_by_account(&self, account: &AccountId) -> Vec<&BridgeTransaction> {
+        self.transactions
+            .iter()
+            .rev()
+            .filter(|t| &t.sender == account || &t.receiver == account)
+            .take(MAX_QUERY_RESULTS)
+            .collect()
+    }
+
+    // Net amount bridged with timestamps in [since, until)
+    pub fn get_volume_in_window(&self, since: u64, until: u64) -> Balance {
+        // Transactions are appended in block order, so the window is a
+        // contiguous slice found by binary search
+        let start = self.transactions.partition_point(|t| t.timestamp < since);
+        let end = self.transactions.partition_point(|t| t.timestamp < until);
+        self.transactions[start..end.max(start)]
+            .iter()
+            .map(|t| t.amount)
+            .sum()
+    }
+
+    pub fn get_completed_count(&self) -> u64 {
+        self.completed_count
+    }
+
     fn validate_transfer(&self, sender: &AccountId, amount: Balance) -> Result<(), String> {
         if amount < self.config.min_transfer {
             return Err("Amount below minimum".to_string());
@@ -151,11 +280,57 @@ impl Bridge {
         Ok(())
     }
 
+    fn prepare_transfer(
+        &self,
+        sender: AccountId,
+        receiver: AccountId,
+        amount: Balance,
+    ) -> Result<BridgeTransaction, String> {
+        // Validate transfer
+        self.validate_transfer(&sender, amount)?;
+        validate_destination(&self.config.target_chain, receiver.as_str())?;
+
+        // Calculate fees
+        let fee = self.calculate_fee(amount);
+        let net_amount = amount - fee;
+
+        Ok(BridgeTransaction {
+            tx_hash: self.generate_tx_hash(&sender, &receiver, amount),
+            from_chain: self.config.source_chain.clone(),
+            to_chain: self.config.target_chain.clone(),
+            sender,
+            receiver,
+            amount: net_amount,
+            timestamp: env::block_timestamp(),
+            status: TransactionStatus::Pending,
+        })
+    }
+
     fn calculate_fee(&self, amount: Balance) -> Balance {
-        amount * BRIDGE_FEE_BPS as u128 / 10_000
+        // Highest tier whose threshold the amount reaches
+        let bps = self.fee_schedule
+            .iter()
+            .rev()
+            .find(|(threshold, _)| amount >= *threshold)
+            .map_or(BRIDGE_FEE_BPS, |&(_, bps)| bps);
+        amount * bps as u128 / 10_000
     }
 
-    fn generate_tx_hash(&self) -> String {
+    fn generate_tx_hash(&self, sender: &AccountId, receiver: &AccountId, amount: Balance) -> String {
+        let nonce = self.tx_nonce.get();
+        self.tx_nonce.set(nonce + 1);
+
+        if self.config.deterministic_tx_hashes {
+            let preimage = format!(
+                "{}:{}:{}:{}:{}:{}",
+                self.config.source_chain, self.config.target_chain, sender, receiver, amount, nonce
+            );
+            return env::sha256(preimage.as_bytes())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+        }
+
         format!(
             "{}_{}_{}",
             env::block_timestamp(),
@@ -190,6 +365,30 @@ impl Bridge {
         Ok(())
     }
 
+    async fn unlock_tokens(&self, transaction: &BridgeTransaction) -> Result<(), String> {
+        let token_contract: Contract = self.get_token_contract(&self.config.token_address)?;
+
+        // Return locked tokens to the sender
+        token_contract
+            .call("transfer")
+            .args_json((
+                transaction.sender.clone(),
+                transaction.amount,
+            ))
+            .transact()
+            .await
+            .map_err(|e| format!("Failed to refund tokens: {}", e))?;
+
+        env::log_str(&format!(
+            "BRIDGE_REFUND:{}:{}:{}",
+            transaction.tx_hash,
+            transaction.amount,
+            transaction.sender
+        ));
+
+        Ok(())
+    }
+
     async fn release_tokens(&self, transaction: &BridgeTransaction) -> Result<(), String> {
         let token_contract: Contract = self.get_token_contract(&self.config.token_address)?;
         
@@ -261,9 +460,16 @@ mod tests {
             min_transfer: MIN_TRANSFER,
             max_transfer: MIN_TRANSFER * 1000,
             confirmation_blocks: CONFIRMATION_BLOCKS,
+            deterministic_tx_hashes: false,
         })
     }
 
+    fn setup_deterministic_bridge() -> Bridge {
+        let mut bridge = setup_bridge();
+        bridge.config.deterministic_tx_hashes = true;
+        bridge
+    }
+
     #[test]
     fn test_transfer_validation() {
         setup_context();
@@ -291,4 +497,185 @@ mod tests {
         
         assert_eq!(fee, amount * BRIDGE_FEE_BPS as u128 / 10_000);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_simulate_transfer() {
+        setup_context();
+        let bridge = setup_bridge();
+
+        let sender: AccountId = "alice.near".parse().unwrap();
+        let receiver: AccountId = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse().unwrap();
+        let amount = MIN_TRANSFER * 10;
+
+        let tx = bridge.simulate_transfer(sender.clone(), receiver, amount).unwrap();
+        assert_eq!(tx.amount, amount - bridge.calculate_fee(amount));
+        assert_eq!(tx.sender, sender);
+        assert!(tx.status == TransactionStatus::Pending);
+
+        // Nothing was recorded
+        assert!(bridge.get_transaction(&tx.tx_hash).is_none());
+        assert_eq!(bridge.total_volume, 0);
+    }
+
+    #[test]
+    fn test_simulate_invalid_transfer() {
+        setup_context();
+        let bridge = setup_bridge();
+
+        let sender: AccountId = "alice.near".parse().unwrap();
+        let receiver: AccountId = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse().unwrap();
+
+        let err = bridge.simulate_transfer(sender, receiver, MIN_TRANSFER - 1).unwrap_err();
+        assert_eq!(err, "Amount below minimum");
+        assert!(bridge.transactions.is_empty());
+    }
+
+    fn record_transaction(bridge: &mut Bridge, sender: &str, receiver: &str, amount: Balance, timestamp: u64) {
+        bridge.transactions.push(BridgeTransaction {
+            tx_hash: format!("tx_{}", bridge.transactions.len()),
+            from_chain: "NEAR".to_string(),
+            to_chain: "Aurora".to_string(),
+            sender: sender.parse().unwrap(),
+            receiver: receiver.parse().unwrap(),
+            amount,
+            timestamp,
+            status: TransactionStatus::Pending,
+        });
+    }
+
+    #[test]
+    fn test_history_queries() {
+        setup_context();
+        let mut bridge = setup_bridge();
+        record_transaction(&mut bridge, "alice.near", "bob.aurora", 100, 1_000);
+        record_transaction(&mut bridge, "carol.near", "alice.near", 200, 2_000);
+        record_transaction(&mut bridge, "carol.near", "bob.aurora", 300, 3_000);
+        record_transaction(&mut bridge, "alice.near", "dave.aurora", 400, 4_000);
+
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let hashes: Vec<&str> = bridge
+            .get_transactions_by_account(&alice)
+            .iter()
+            .map(|t| t.tx_hash.as_str())
+            .collect();
+        assert_eq!(hashes, vec!["tx_3", "tx_1", "tx_0"]);
+
+        assert_eq!(bridge.get_volume_in_window(2_000, 4_000), 500);
+        assert_eq!(bridge.get_volume_in_window(0, u64::MAX), 1_000);
+        assert_eq!(bridge.get_volume_in_window(5_000, 6_000), 0);
+        assert_eq!(bridge.get_completed_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_refund_timed_out_transfer() {
+        setup_context();
+        let mut bridge = setup_bridge();
+        record_transaction(&mut bridge, "alice.near", "bob.aurora", 100, 1_000_000);
+
+        assert_eq!(
+            bridge.refund_transfer("tx_0").await.unwrap_err(),
+            "Refund timeout not reached"
+        );
+
+        let context = VMContextBuilder::new()
+            .predecessor_account_id("alice.near".parse().unwrap())
+            .block_timestamp(1_000_000 + REFUND_TIMEOUT)
+            .build();
+        testing_env!(context);
+
+        bridge.refund_transfer("tx_0").await.unwrap();
+        assert!(bridge.get_transaction("tx_0").unwrap().status == TransactionStatus::Failed);
+        assert!(bridge.get_pending_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refund_rejects_completed_transfer() {
+        setup_context();
+        let mut bridge = setup_bridge();
+        bridge.set_refund_timeout(0);
+        record_transaction(&mut bridge, "alice.near", "bob.aurora", 100, 1_000_000);
+        bridge.transactions[0].status = TransactionStatus::Completed;
+
+        assert_eq!(
+            bridge.refund_transfer("tx_0").await.unwrap_err(),
+            "Transaction already completed"
+        );
+    }
+
+    #[test]
+    fn test_tiered_fees() {
+        setup_context();
+        let mut bridge = setup_bridge();
+        let (mid, large) = (MIN_TRANSFER * 100, MIN_TRANSFER * 500);
+        bridge.set_fee_schedule(vec![(0, 30), (mid, 20), (large, 10)]).unwrap();
+
+        assert_eq!(bridge.calculate_fee(mid - 1), (mid - 1) * 30 / 10_000);
+        assert_eq!(bridge.calculate_fee(mid), mid * 20 / 10_000);
+        assert_eq!(bridge.calculate_fee(large - 1), (large - 1) * 20 / 10_000);
+        assert_eq!(bridge.calculate_fee(large), large * 10 / 10_000);
+    }
+
+    #[test]
+    fn test_fee_schedule_must_be_monotonic() {
+        setup_context();
+        let mut bridge = setup_bridge();
+
+        assert!(bridge.set_fee_schedule(vec![(MIN_TRANSFER, 30)]).is_err());
+        assert!(bridge.set_fee_schedule(vec![(0, 30), (500, 20), (500, 10)]).is_err());
+        assert!(bridge.set_fee_schedule(vec![(0, 20), (500, 30)]).is_err());
+
+        // A rejected schedule leaves the flat fee in place
+        assert_eq!(bridge.calculate_fee(10_000), 10_000 * BRIDGE_FEE_BPS as u128 / 10_000);
+    }
+
+    #[test]
+    fn test_validate_destination() {
+        assert!(validate_destination("Aurora", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").is_ok());
+        assert!(validate_destination("ethereum", "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_ok());
+        assert!(validate_destination("ethereum", "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").is_err());
+        assert!(validate_destination("ethereum", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea").is_err());
+        assert!(validate_destination("ethereum", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beazz").is_err());
+
+        assert!(validate_destination("NEAR", "bob.near").is_ok());
+        assert!(validate_destination("near", "app.bob.near").is_ok());
+        assert!(validate_destination("near", &"ab".repeat(32)).is_ok());
+        assert!(validate_destination("near", ".near").is_err());
+        assert!(validate_destination("near", "bob.aurora").is_err());
+        assert!(validate_destination("near", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").is_err());
+
+        assert!(validate_destination("solana", "bob.near").is_err());
+    }
+
+    #[test]
+    fn test_transfer_rejects_malformed_destination() {
+        setup_context();
+        let bridge = setup_bridge();
+
+        let sender: AccountId = "alice.near".parse().unwrap();
+        let receiver: AccountId = "bob.aurora".parse().unwrap();
+
+        let err = bridge.simulate_transfer(sender, receiver, MIN_TRANSFER * 10).unwrap_err();
+        assert!(err.starts_with("Invalid aurora address"));
+    }
+
+    #[test]
+    fn test_deterministic_tx_hashes() {
+        setup_context();
+        let sender: AccountId = "alice.near".parse().unwrap();
+        let receiver: AccountId = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse().unwrap();
+        let amount = MIN_TRANSFER * 10;
+
+        let bridge = setup_deterministic_bridge();
+        let first = bridge.simulate_transfer(sender.clone(), receiver.clone(), amount).unwrap();
+        let second = bridge.simulate_transfer(sender.clone(), receiver.clone(), amount).unwrap();
+
+        // Identical inputs still get a fresh hash per call
+        assert_ne!(first.tx_hash, second.tx_hash);
+        assert_eq!(first.tx_hash.len(), 64);
+
+        // A fresh bridge replays the same sequence
+        let replay = setup_deterministic_bridge();
+        assert_eq!(replay.simulate_transfer(sender.clone(), receiver.clone(), amount).unwrap().tx_hash, first.tx_hash);
+        assert_eq!(replay.simulate_transfer(sender, receiver, amount).unwrap().tx_hash, second.tx_hash);
+    }
+}
@@ -11,7 +11,7 @@ mod tests {
 
         let mut bridge = setup_bridge();
         let sender = AccountId::new_unchecked("alice.near".to_string());
-        let receiver = AccountId::new_unchecked("bob.aurora".to_string());
+        let receiver = AccountId::new_unchecked("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".to_string());
         let amount = U128(1_000_000);
 
         // Test transfer initiation
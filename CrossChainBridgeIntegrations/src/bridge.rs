@@ -1,24 +1,26 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
+use async_trait::async_trait;
 use dashmap::DashMap;
 use futures::{stream, StreamExt};
 use lru::LruCache;
-use metrics::{counter, gauge};
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use metrics::{register_counter, register_gauge};
 use near_sdk::serde::{Deserialize, Serialize};
 use parking_lot::RwLock;
-use rust_decimal::Decimal;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Semaphore;
-use tokio_stream::wrappers::ReceiverStream;
-use tracing::{info, warn, error};
+use tracing::{warn, error};
 
 // Constants for performance tuning
 const MAX_BATCH_SIZE: usize = 50;
 const MAX_CONCURRENT_TRANSFERS: usize = 10;
 const CACHE_TTL: Duration = Duration::from_secs(60);
-const MAX_RETRIES: u32 = 3;
+const TOKEN_CACHE_CAPACITY: usize = 100;
 const GAS_PRICE_CACHE_TTL: Duration = Duration::from_secs(30);
+const RETRY_INITIAL_INTERVAL_MS: u64 = 500;
+const RETRY_MAX_INTERVAL_MS: u64 = 60_000;
+const RETRY_MAX_ELAPSED_SECS: u64 = 300;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BridgeConfig {
@@ -30,8 +32,78 @@ pub struct BridgeConfig {
     pub max_gas_price: u64,
     pub min_transfer_amount: u64,
     pub max_transfer_amount: u64,
+    #[serde(default = "default_max_concurrent_transfers")]
+    pub max_concurrent_transfers: usize,
+    #[serde(default = "default_retry_initial_interval_ms")]
+    pub retry_initial_interval_ms: u64,
+    #[serde(default = "default_retry_max_interval_ms")]
+    pub retry_max_interval_ms: u64,
+    #[serde(default = "default_retry_max_elapsed_secs")]
+    pub retry_max_elapsed_secs: u64,
 }
 
+fn default_max_concurrent_transfers() -> usize {
+    MAX_CONCURRENT_TRANSFERS
+}
+
+fn default_retry_initial_interval_ms() -> u64 {
+    RETRY_INITIAL_INTERVAL_MS
+}
+
+fn default_retry_max_interval_ms() -> u64 {
+    RETRY_MAX_INTERVAL_MS
+}
+
+fn default_retry_max_elapsed_secs() -> u64 {
+    RETRY_MAX_ELAPSED_SECS
+}
+
+// Rescales `amount` from `from_decimals` to `to_decimals` base units.
+// Scaling down must be exact; dropping digits would silently burn funds.
+pub fn normalize_amount(amount: u64, from_decimals: u8, to_decimals: u8) -> Result<u64> {
+    if to_decimals >= from_decimals {
+        let factor = 10u64
+            .checked_pow((to_decimals - from_decimals) as u32)
+            .ok_or_else(|| anyhow::anyhow!("Decimal gap too large to bridge"))?;
+        amount
+            .checked_mul(factor)
+            .ok_or_else(|| anyhow::anyhow!("Amount {} overflows at {} decimals", amount, to_decimals))
+    } else {
+        let factor = 10u64
+            .checked_pow((from_decimals - to_decimals) as u32)
+            .ok_or_else(|| anyhow::anyhow!("Decimal gap too large to bridge"))?;
+        if !amount.is_multiple_of(factor) {
+            return Err(anyhow::anyhow!(
+                "Amount {} would lose precision at {} decimals",
+                amount,
+                to_decimals
+            ));
+        }
+        Ok(amount / factor)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum TransferValidationError {
+    #[error("Transfer amount below minimum: {amount} < {min}")]
+    BelowMin { amount: u64, min: u64 },
+
+    #[error("Transfer amount above maximum: {amount} > {max}")]
+    AboveMax { amount: u64, max: u64 },
+
+    #[error("Transfer deadline expired at {deadline}")]
+    Expired { deadline: u64 },
+
+    #[error("{0}")]
+    InvalidDestination(String),
+}
+
+// A failure that retrying cannot fix. Chain clients wrap errors in this to
+// stop the bridge from retrying them.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct PermanentError(pub String);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferRequest {
     pub sender: String,
@@ -68,44 +140,221 @@ pub struct TokenInfo {
     pub cached_at: std::time::Instant,
 }
 
+const EVM_CHAINS: [&str; 6] = ["ethereum", "aurora", "polygon", "arbitrum", "optimism", "bsc"];
+
+// Checks that `address` is well-formed for the destination chain: 0x-prefixed
+// 20-byte hex on EVM chains, a named `.near` account or 64-hex implicit
+// account on NEAR
+pub fn validate_destination(chain: &str, address: &str) -> Result<(), String> {
+    let chain = chain.to_lowercase();
+
+    if EVM_CHAINS.contains(&chain.as_str()) {
+        let valid = address
+            .strip_prefix("0x")
+            .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+        if !valid {
+            return Err(format!("Invalid {} address: {}", chain, address));
+        }
+        return Ok(());
+    }
+
+    if chain == "near" {
+        let implicit = address.len() == 64
+            && address.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+        let named = address
+            .strip_suffix(".near")
+            .is_some_and(|name| {
+                !name.is_empty()
+                    && name.split('.').all(|part| {
+                        !part.is_empty()
+                            && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+                    })
+            });
+        if !implicit && !named {
+            return Err(format!("Invalid near address: {}", address));
+        }
+        return Ok(());
+    }
+
+    Err(format!("Unsupported destination chain: {}", chain))
+}
+
+// Chain-specific operations the bridge drives
+#[async_trait]
+pub trait ChainClient: Send + Sync {
+    async fn execute_transfer(&self, transfer: &TransferRequest, token_info: &TokenInfo, gas_price: u64) -> Result<String>;
+    async fn fetch_token_info(&self, token_address: &str) -> Result<TokenInfo>;
+    async fn fetch_gas_price(&self) -> Result<u64>;
+    async fn check_transfer_status(&self, tx_hash: &str) -> Result<TransferState>;
+}
+
+// Stand-in until a chain client is supplied
+struct UnconfiguredClient;
+
+#[async_trait]
+impl ChainClient for UnconfiguredClient {
+    async fn execute_transfer(&self, _transfer: &TransferRequest, _token_info: &TokenInfo, _gas_price: u64) -> Result<String> {
+        Err(anyhow::anyhow!("No chain client configured"))
+    }
+
+    async fn fetch_token_info(&self, _token_address: &str) -> Result<TokenInfo> {
+        Err(anyhow::anyhow!("No chain client configured"))
+    }
+
+    async fn fetch_gas_price(&self) -> Result<u64> {
+        Err(anyhow::anyhow!("No chain client configured"))
+    }
+
+    async fn check_transfer_status(&self, _tx_hash: &str) -> Result<TransferState> {
+        Err(anyhow::anyhow!("No chain client configured"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenCacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub total_transfers: u64,
+    pub failed_transfers: u64,
+    pub active_transfers: u64,
+    pub average_confirmation_time: f64, // seconds
+    pub gas_price: u64,
+}
+
 pub struct Bridge {
     config: BridgeConfig,
+    client: Arc<dyn ChainClient>,
     pending_transfers: Arc<DashMap<String, TransferStatus>>,
     token_cache: Arc<RwLock<LruCache<String, TokenInfo>>>,
+    token_cache_hits: AtomicU64,
+    token_cache_misses: AtomicU64,
     gas_price_cache: Arc<RwLock<(u64, std::time::Instant)>>,
     transfer_semaphore: Arc<Semaphore>,
     metrics: Arc<Metrics>,
 }
 
-#[derive(Debug)]
 struct Metrics {
     total_transfers: metrics::Counter,
     failed_transfers: metrics::Counter,
     active_transfers: metrics::Gauge,
     average_confirmation_time: metrics::Gauge,
     gas_price: metrics::Gauge,
+    // Exported values can't be read back from the recorder, so they are
+    // mirrored here for snapshots
+    total_count: AtomicU64,
+    failed_count: AtomicU64,
+    active_count: AtomicU64,
+    confirmed_count: AtomicU64,
+    confirmation_secs_total: AtomicU64,
+    gas_price_value: AtomicU64,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Self {
+            total_transfers: register_counter!("bridge_total_transfers"),
+            failed_transfers: register_counter!("bridge_failed_transfers"),
+            active_transfers: register_gauge!("bridge_active_transfers"),
+            average_confirmation_time: register_gauge!("bridge_avg_confirmation_time"),
+            gas_price: register_gauge!("bridge_gas_price"),
+            total_count: AtomicU64::new(0),
+            failed_count: AtomicU64::new(0),
+            active_count: AtomicU64::new(0),
+            confirmed_count: AtomicU64::new(0),
+            confirmation_secs_total: AtomicU64::new(0),
+            gas_price_value: AtomicU64::new(0),
+        }
+    }
+
+    fn record_transfer(&self) {
+        self.total_transfers.increment(1);
+        self.active_transfers.increment(1.0);
+        self.total_count.fetch_add(1, Ordering::Relaxed);
+        self.active_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) {
+        self.failed_transfers.increment(1);
+        self.failed_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // A transfer left the active set; `confirmation_secs` is set when it completed
+    fn record_settled(&self, confirmation_secs: Option<u64>) {
+        self.active_transfers.decrement(1.0);
+        let _ = self.active_count.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+
+        if let Some(secs) = confirmation_secs {
+            let total = self.confirmation_secs_total.fetch_add(secs, Ordering::Relaxed) + secs;
+            let count = self.confirmed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            self.average_confirmation_time.set(total as f64 / count as f64);
+        }
+    }
+
+    fn record_gas_price(&self, price: u64) {
+        self.gas_price.set(price as f64);
+        self.gas_price_value.store(price, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> MetricsSnapshot {
+        let confirmed = self.confirmed_count.load(Ordering::Relaxed);
+        let average_confirmation_time = if confirmed == 0 {
+            0.0
+        } else {
+            self.confirmation_secs_total.load(Ordering::Relaxed) as f64 / confirmed as f64
+        };
+
+        MetricsSnapshot {
+            total_transfers: self.total_count.load(Ordering::Relaxed),
+            failed_transfers: self.failed_count.load(Ordering::Relaxed),
+            active_transfers: self.active_count.load(Ordering::Relaxed),
+            average_confirmation_time,
+            gas_price: self.gas_price_value.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 impl Bridge {
     pub fn new(config: BridgeConfig) -> Self {
-        let metrics = Arc::new(Metrics {
-            total_transfers: counter!("bridge_total_transfers"),
-            failed_transfers: counter!("bridge_failed_transfers"),
-            active_transfers: gauge!("bridge_active_transfers"),
-            average_confirmation_time: gauge!("bridge_avg_confirmation_time"),
-            gas_price: gauge!("bridge_gas_price"),
-        });
+        Self::with_client(config, Arc::new(UnconfiguredClient))
+    }
+
+    pub fn with_client(config: BridgeConfig, client: Arc<dyn ChainClient>) -> Self {
+        assert!(config.max_concurrent_transfers >= 1, "max_concurrent_transfers must be at least 1");
+        let metrics = Arc::new(Metrics::new());
+        let transfer_semaphore = Arc::new(Semaphore::new(config.max_concurrent_transfers));
 
         Self {
             config,
+            client,
             pending_transfers: Arc::new(DashMap::new()),
-            token_cache: Arc::new(RwLock::new(LruCache::new(100))),
+            token_cache: Arc::new(RwLock::new(LruCache::new(
+                std::num::NonZeroUsize::new(TOKEN_CACHE_CAPACITY).expect("capacity is non-zero"),
+            ))),
+            token_cache_hits: AtomicU64::new(0),
+            token_cache_misses: AtomicU64::new(0),
             gas_price_cache: Arc::new(RwLock::new((0, std::time::Instant::now()))),
-            transfer_semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_TRANSFERS)),
+            transfer_semaphore,
             metrics,
         }
     }
 
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
     pub async fn batch_transfer(&self, transfers: Vec<TransferRequest>) -> Result<Vec<String>> {
         // Validate batch size
         if transfers.is_empty() {
@@ -143,7 +392,7 @@ impl Bridge {
         // Process transfers in parallel with bounded concurrency
         let results: Vec<Result<String>> = stream::iter(batch)
             .map(|transfer| self.execute_single_transfer(transfer, gas_price))
-            .buffer_unordered(MAX_CONCURRENT_TRANSFERS)
+            .buffer_unordered(self.config.max_concurrent_transfers)
             .collect()
             .await;
 
@@ -154,7 +403,7 @@ impl Bridge {
                 Ok(hash) => tx_hashes.push(hash),
                 Err(e) => {
                     error!("Transfer failed: {}", e);
-                    self.metrics.failed_transfers.increment(1);
+                    self.metrics.record_failure();
                 }
             }
         }
@@ -169,12 +418,27 @@ impl Bridge {
         // Get cached token info or fetch it
         let token_info = self.get_token_info(&transfer.token).await?;
 
+        // Express the amount in the bridged token's base units on the target chain
+        let destination_info = self.get_token_info(&self.config.token_address).await?;
+        let mut scaled = transfer.clone();
+        scaled.amount = normalize_amount(transfer.amount, token_info.decimals, destination_info.decimals)?;
+
         // Execute transfer with retry logic
-        let tx_hash = self.execute_transfer_with_retry(transfer, &token_info, gas_price).await?;
+        let tx_hash = self.execute_transfer_with_retry(&scaled, &token_info, gas_price).await?;
+
+        // Track until the target chain settles it
+        self.pending_transfers.insert(tx_hash.clone(), TransferStatus {
+            tx_hash: tx_hash.clone(),
+            from_chain: self.config.source_chain.clone(),
+            to_chain: self.config.target_chain.clone(),
+            amount: transfer.amount,
+            timestamp: unix_now(),
+            status: TransferState::Pending,
+            retries: 0,
+        });
 
         // Update metrics
-        self.metrics.total_transfers.increment(1);
-        self.metrics.active_transfers.increment(1);
+        self.metrics.record_transfer();
 
         Ok(tx_hash)
     }
@@ -185,12 +449,22 @@ impl Bridge {
         token_info: &TokenInfo,
         gas_price: u64,
     ) -> Result<String> {
-        let mut backoff = backoff::ExponentialBackoff::default();
-        backoff.max_elapsed_time = Some(Duration::from_secs(300)); // 5 minutes max
+        // Full jitter: each wait is drawn from [0, 2x] of the current interval so
+        // a failing batch doesn't retry in lockstep
+        let backoff = backoff::ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_millis(self.config.retry_initial_interval_ms))
+            .with_max_interval(Duration::from_millis(self.config.retry_max_interval_ms))
+            .with_max_elapsed_time(Some(Duration::from_secs(self.config.retry_max_elapsed_secs)))
+            .with_randomization_factor(1.0)
+            .build();
 
         backoff::future::retry(backoff, || async {
             match self.execute_transfer_internal(transfer, token_info, gas_price).await {
                 Ok(hash) => Ok(hash),
+                Err(e) if e.is::<PermanentError>() => {
+                    warn!("Transfer failed permanently: {}", e);
+                    Err(backoff::Error::permanent(e))
+                }
                 Err(e) => {
                     warn!("Transfer retry needed: {}", e);
                     Err(backoff::Error::transient(e))
@@ -201,37 +475,61 @@ impl Bridge {
     }
 
     async fn get_current_gas_price(&self) -> Result<u64> {
-        let cache = self.gas_price_cache.read();
-        let (cached_price, cached_at) = (*cache.0, cache.1);
+        let (cached_price, cached_at) = *self.gas_price_cache.read();
         
-        if cached_at.elapsed() < GAS_PRICE_CACHE_TTL {
+        // A zero price means nothing has been fetched yet
+        if cached_price > 0 && cached_at.elapsed() < GAS_PRICE_CACHE_TTL {
             return Ok(cached_price);
         }
-        drop(cache);
 
         // Fetch new gas price
         let new_price = self.fetch_gas_price().await?;
         *self.gas_price_cache.write() = (new_price, std::time::Instant::now());
-        self.metrics.gas_price.set(new_price as f64);
+        self.metrics.record_gas_price(new_price);
         
         Ok(new_price)
     }
 
     async fn get_token_info(&self, token_address: &str) -> Result<TokenInfo> {
         // Try to get from cache first
-        if let Some(info) = self.token_cache.read().get(token_address) {
+        let cached = self.token_cache.write().get(token_address).cloned();
+        if let Some(info) = cached {
             if info.cached_at.elapsed() < CACHE_TTL {
-                return Ok(info.clone());
+                self.token_cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(info);
             }
         }
+        self.token_cache_misses.fetch_add(1, Ordering::Relaxed);
 
         // Fetch fresh token info
-        let info = self.fetch_token_info(token_address).await?;
+        self.refresh_token(token_address).await
+    }
+
+    // Drops the cached metadata so the next lookup re-fetches it. A token
+    // that isn't cached is left alone.
+    pub fn invalidate_token(&self, token_address: &str) {
+        self.token_cache.write().pop(token_address);
+    }
+
+    // Re-fetches token metadata now, replacing any cached copy
+    pub async fn refresh_token(&self, token_address: &str) -> Result<TokenInfo> {
+        let mut info = self.fetch_token_info(token_address).await?;
+        info.cached_at = std::time::Instant::now();
         self.token_cache.write().put(token_address.to_string(), info.clone());
 
         Ok(info)
     }
 
+    pub fn token_cache_stats(&self) -> TokenCacheStats {
+        let cache = self.token_cache.read();
+        TokenCacheStats {
+            entries: cache.len(),
+            capacity: cache.cap().get(),
+            hits: self.token_cache_hits.load(Ordering::Relaxed),
+            misses: self.token_cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
     pub async fn monitor_pending_transfers(&self) {
         let mut interval = tokio::time::interval(Duration::from_secs(10));
 
@@ -249,7 +547,7 @@ impl Bridge {
             .collect();
 
         stream::iter(pending)
-            .for_each_concurrent(MAX_CONCURRENT_TRANSFERS, |tx_hash| async move {
+            .for_each_concurrent(self.config.max_concurrent_transfers, |tx_hash| async move {
                 if let Err(e) = self.check_transfer_status(&tx_hash).await {
                     error!("Failed to check transfer status: {}", e);
                 }
@@ -257,40 +555,390 @@ impl Bridge {
             .await;
     }
 
-    async fn validate_transfer(&self, transfer: &TransferRequest) -> Result<()> {
+    async fn validate_transfer(&self, transfer: &TransferRequest) -> std::result::Result<(), TransferValidationError> {
         // Amount validation
         if transfer.amount < self.config.min_transfer_amount {
-            return Err(anyhow::anyhow!("Transfer amount below minimum"));
+            return Err(TransferValidationError::BelowMin {
+                amount: transfer.amount,
+                min: self.config.min_transfer_amount,
+            });
         }
         if transfer.amount > self.config.max_transfer_amount {
-            return Err(anyhow::anyhow!("Transfer amount above maximum"));
+            return Err(TransferValidationError::AboveMax {
+                amount: transfer.amount,
+                max: self.config.max_transfer_amount,
+            });
         }
 
+        // Receiver must be addressable on the target chain
+        validate_destination(&self.config.target_chain, &transfer.receiver)
+            .map_err(TransferValidationError::InvalidDestination)?;
+
         // Deadline validation
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
-        if transfer.deadline <= current_time {
-            return Err(anyhow::anyhow!("Transfer deadline expired"));
+        if transfer.deadline <= unix_now() {
+            return Err(TransferValidationError::Expired { deadline: transfer.deadline });
         }
 
         Ok(())
     }
 
-    // Placeholder methods that need to be implemented based on specific chain requirements
+    // Chain-specific work is delegated to the configured client
     async fn execute_transfer_internal(&self, transfer: &TransferRequest, token_info: &TokenInfo, gas_price: u64) -> Result<String> {
-        unimplemented!("Implement chain-specific transfer logic")
+        self.client.execute_transfer(transfer, token_info, gas_price).await
     }
 
     async fn fetch_token_info(&self, token_address: &str) -> Result<TokenInfo> {
-        unimplemented!("Implement chain-specific token info fetching")
+        self.client.fetch_token_info(token_address).await
     }
 
     async fn fetch_gas_price(&self) -> Result<u64> {
-        unimplemented!("Implement chain-specific gas price fetching")
+        self.client.fetch_gas_price().await
     }
 
     async fn check_transfer_status(&self, tx_hash: &str) -> Result<()> {
-        unimplemented!("Implement chain-specific status checking")
+        let state = self.client.check_transfer_status(tx_hash).await?;
+
+        let Some(mut entry) = self.pending_transfers.get_mut(tx_hash) else {
+            return Ok(());
+        };
+        match &state {
+            TransferState::Completed => {
+                let elapsed = unix_now().saturating_sub(entry.timestamp);
+                self.metrics.record_settled(Some(elapsed));
+            }
+            TransferState::Failed(reason) => {
+                warn!("Transfer {} failed on target chain: {}", tx_hash, reason);
+                self.metrics.record_failure();
+                self.metrics.record_settled(None);
+            }
+            _ => {}
+        }
+        entry.status = state;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVM_RECEIVER: &str = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+
+    struct MockClient {
+        gas_price: u64,
+        settled_state: TransferState,
+        sent: AtomicU64,
+        delay: Duration,
+        // Transient failures to return before succeeding
+        transient_failures: AtomicU64,
+        permanent_failure: bool,
+        attempts: parking_lot::Mutex<Vec<std::time::Instant>>,
+        token_fetches: AtomicU64,
+        decimals: std::collections::HashMap<String, u8>,
+        sent_amounts: parking_lot::Mutex<Vec<u64>>,
+    }
+
+    impl MockClient {
+        fn new(gas_price: u64) -> Self {
+            Self {
+                gas_price,
+                settled_state: TransferState::Completed,
+                sent: AtomicU64::new(0),
+                delay: Duration::ZERO,
+                transient_failures: AtomicU64::new(0),
+                permanent_failure: false,
+                attempts: parking_lot::Mutex::new(Vec::new()),
+                token_fetches: AtomicU64::new(0),
+                decimals: std::collections::HashMap::new(),
+                sent_amounts: parking_lot::Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ChainClient for MockClient {
+        async fn execute_transfer(&self, transfer: &TransferRequest, _token_info: &TokenInfo, _gas_price: u64) -> Result<String> {
+            self.attempts.lock().push(std::time::Instant::now());
+            tokio::time::sleep(self.delay).await;
+            if self.permanent_failure {
+                return Err(PermanentError("Token not supported".to_string()).into());
+            }
+            let remaining = self.transient_failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.transient_failures.store(remaining - 1, Ordering::SeqCst);
+                return Err(anyhow::anyhow!("RPC timeout"));
+            }
+            let n = self.sent.fetch_add(1, Ordering::SeqCst);
+            self.sent_amounts.lock().push(transfer.amount);
+            Ok(format!("0xtx{}", n))
+        }
+
+        async fn fetch_token_info(&self, token_address: &str) -> Result<TokenInfo> {
+            self.token_fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(TokenInfo {
+                decimals: self.decimals.get(token_address).copied().unwrap_or(18),
+                symbol: "TKN".to_string(),
+                total_supply: 1_000_000_000,
+                cached_at: std::time::Instant::now(),
+            })
+        }
+
+        async fn fetch_gas_price(&self) -> Result<u64> {
+            Ok(self.gas_price)
+        }
+
+        async fn check_transfer_status(&self, _tx_hash: &str) -> Result<TransferState> {
+            Ok(self.settled_state.clone())
+        }
+    }
+
+    fn test_config(target_chain: &str) -> BridgeConfig {
+        BridgeConfig {
+            source_chain: "near".to_string(),
+            target_chain: target_chain.to_string(),
+            token_address: "token.near".to_string(),
+            bridge_address: "bridge.near".to_string(),
+            confirmation_blocks: 10,
+            max_gas_price: 100,
+            min_transfer_amount: 1,
+            max_transfer_amount: 1_000_000,
+            max_concurrent_transfers: MAX_CONCURRENT_TRANSFERS,
+            retry_initial_interval_ms: 20,
+            retry_max_interval_ms: 100,
+            retry_max_elapsed_secs: 5,
+        }
+    }
+
+    fn transfer_to(receiver: &str) -> TransferRequest {
+        TransferRequest {
+            sender: "alice.near".to_string(),
+            receiver: receiver.to_string(),
+            token: "token.near".to_string(),
+            amount: 1_000,
+            deadline: u64::MAX,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_evm_destination() {
+        let bridge = Bridge::new(test_config("ethereum"));
+
+        assert!(bridge.validate_transfer(&transfer_to("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")).await.is_ok());
+        assert!(bridge.validate_transfer(&transfer_to("bob.near")).await.is_err());
+        assert!(bridge.validate_transfer(&transfer_to("0x1234")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_near_destination() {
+        let bridge = Bridge::new(test_config("near"));
+
+        assert!(bridge.validate_transfer(&transfer_to("bob.near")).await.is_ok());
+        assert!(bridge.validate_transfer(&transfer_to(&"0f".repeat(32))).await.is_ok());
+        assert!(bridge.validate_transfer(&transfer_to("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")).await.is_err());
+        assert!(bridge.validate_transfer(&transfer_to("Bob.near")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_snapshot_counts_transfers() {
+        let bridge = Bridge::with_client(test_config("ethereum"), Arc::new(MockClient::new(42)));
+
+        let mut too_large = transfer_to(EVM_RECEIVER);
+        too_large.amount = 10_000_000;
+        let hashes = bridge
+            .batch_transfer(vec![transfer_to(EVM_RECEIVER), transfer_to(EVM_RECEIVER), too_large])
+            .await
+            .unwrap();
+        assert_eq!(hashes.len(), 2);
+
+        let snapshot = bridge.metrics_snapshot();
+        assert_eq!(snapshot.total_transfers, 2);
+        assert_eq!(snapshot.failed_transfers, 1);
+        assert_eq!(snapshot.active_transfers, 2);
+        assert_eq!(snapshot.gas_price, 42);
+
+        // Both transfers settle on the next status sweep
+        bridge.process_pending_transfers().await;
+        let snapshot = bridge.metrics_snapshot();
+        assert_eq!(snapshot.active_transfers, 0);
+        assert_eq!(snapshot.total_transfers, 2);
+        assert!(snapshot.average_confirmation_time >= 0.0);
+    }
+
+    async fn time_batch(max_concurrent_transfers: usize) -> Duration {
+        let mut config = test_config("ethereum");
+        config.max_concurrent_transfers = max_concurrent_transfers;
+        let mut client = MockClient::new(42);
+        client.delay = Duration::from_millis(50);
+        let bridge = Bridge::with_client(config, Arc::new(client));
+
+        let start = std::time::Instant::now();
+        let hashes = bridge.batch_transfer(vec![transfer_to(EVM_RECEIVER); 4]).await.unwrap();
+        assert_eq!(hashes.len(), 4);
+        start.elapsed()
+    }
+
+    #[tokio::test]
+    async fn test_configured_transfer_concurrency() {
+        // One at a time: four sequential 50ms sends
+        assert!(time_batch(1).await >= Duration::from_millis(200));
+        // Four wide: the sends overlap
+        assert!(time_batch(4).await < Duration::from_millis(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_concurrent_transfers must be at least 1")]
+    fn test_zero_concurrency_rejected() {
+        let mut config = test_config("ethereum");
+        config.max_concurrent_transfers = 0;
+        Bridge::new(config);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_is_not_retried() {
+        let mut client = MockClient::new(42);
+        client.permanent_failure = true;
+        let client = Arc::new(client);
+        let bridge = Bridge::with_client(test_config("ethereum"), client.clone());
+
+        let hashes = bridge.batch_transfer(vec![transfer_to(EVM_RECEIVER)]).await.unwrap();
+        assert!(hashes.is_empty());
+        assert_eq!(client.attempts.lock().len(), 1);
+        assert_eq!(bridge.metrics_snapshot().failed_transfers, 1);
+    }
+
+    #[tokio::test]
+    async fn test_transient_error_retries_with_jitter() {
+        let client = MockClient::new(42);
+        client.transient_failures.store(4, Ordering::SeqCst);
+        let client = Arc::new(client);
+        let bridge = Bridge::with_client(test_config("ethereum"), client.clone());
+
+        let hashes = bridge.batch_transfer(vec![transfer_to(EVM_RECEIVER)]).await.unwrap();
+        assert_eq!(hashes.len(), 1);
+
+        let attempts = client.attempts.lock();
+        assert_eq!(attempts.len(), 5);
+        let gaps: Vec<Duration> = attempts.windows(2).map(|w| w[1] - w[0]).collect();
+        // Randomized waits don't line up on the fixed exponential schedule
+        assert!(gaps.windows(2).any(|w| w[0] != w[1]));
+        assert!(gaps.iter().all(|gap| *gap <= Duration::from_millis(250)));
+    }
+
+    #[tokio::test]
+    async fn test_invalidated_token_is_refetched() {
+        let client = Arc::new(MockClient::new(42));
+        let bridge = Bridge::with_client(test_config("ethereum"), client.clone());
+
+        bridge.get_token_info("token.near").await.unwrap();
+        bridge.get_token_info("token.near").await.unwrap();
+        assert_eq!(client.token_fetches.load(Ordering::SeqCst), 1);
+
+        // Unknown tokens are a no-op
+        bridge.invalidate_token("other.near");
+        bridge.get_token_info("token.near").await.unwrap();
+        assert_eq!(client.token_fetches.load(Ordering::SeqCst), 1);
+
+        bridge.invalidate_token("token.near");
+        assert_eq!(bridge.token_cache_stats().entries, 0);
+        bridge.get_token_info("token.near").await.unwrap();
+        assert_eq!(client.token_fetches.load(Ordering::SeqCst), 2);
+
+        bridge.refresh_token("token.near").await.unwrap();
+        assert_eq!(client.token_fetches.load(Ordering::SeqCst), 3);
+
+        let stats = bridge.token_cache_stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.capacity, TOKEN_CACHE_CAPACITY);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn test_normalize_amount() {
+        assert_eq!(normalize_amount(1_500_000, 6, 18).unwrap(), 1_500_000_000_000_000_000);
+        assert_eq!(normalize_amount(2_000_000_000_000, 18, 6).unwrap(), 2);
+        assert_eq!(normalize_amount(42, 8, 8).unwrap(), 42);
+        assert!(normalize_amount(2_000_000_000_001, 18, 6).is_err());
+        assert!(normalize_amount(u64::MAX, 6, 18).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_scaled_to_destination_decimals() {
+        let mut config = test_config("ethereum");
+        config.max_transfer_amount = u64::MAX;
+        let mut client = MockClient::new(42);
+        client.decimals.insert("usdc.near".to_string(), 6);
+        client.decimals.insert("token.near".to_string(), 18);
+        client.decimals.insert("wbtc.near".to_string(), 8);
+        let client = Arc::new(client);
+        let bridge = Bridge::with_client(config, client.clone());
+
+        // 6 -> 18 decimals
+        let mut usdc = transfer_to(EVM_RECEIVER);
+        usdc.token = "usdc.near".to_string();
+        usdc.amount = 2_500_000;
+
+        // 8 -> 18 decimals
+        let mut wbtc = transfer_to(EVM_RECEIVER);
+        wbtc.token = "wbtc.near".to_string();
+        wbtc.amount = 1;
+
+        bridge.batch_transfer(vec![usdc]).await.unwrap();
+        bridge.batch_transfer(vec![wbtc]).await.unwrap();
+        assert_eq!(*client.sent_amounts.lock(), vec![2_500_000_000_000_000_000, 10_000_000_000]);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_losing_precision_rejected() {
+        let mut config = test_config("ethereum");
+        config.token_address = "usdc.near".to_string();
+        config.max_transfer_amount = u64::MAX;
+        let mut client = MockClient::new(42);
+        client.decimals.insert("usdc.near".to_string(), 6);
+        let client = Arc::new(client);
+        let bridge = Bridge::with_client(config, client.clone());
+
+        // 18 -> 6 decimals with dust below the destination's precision
+        let mut transfer = transfer_to(EVM_RECEIVER);
+        transfer.token = "weth.near".to_string();
+        transfer.amount = 1_000_000_000_123;
+
+        let hashes = bridge.batch_transfer(vec![transfer.clone()]).await.unwrap();
+        assert!(hashes.is_empty());
+        assert_eq!(bridge.metrics_snapshot().failed_transfers, 1);
+
+        transfer.amount = 1_000_000_000_000;
+        bridge.batch_transfer(vec![transfer]).await.unwrap();
+        assert_eq!(*client.sent_amounts.lock(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_validation_errors_are_typed() {
+        let bridge = Bridge::new(test_config("ethereum"));
+
+        let mut transfer = transfer_to(EVM_RECEIVER);
+        transfer.amount = 0;
+        assert_eq!(
+            bridge.validate_transfer(&transfer).await,
+            Err(TransferValidationError::BelowMin { amount: 0, min: 1 })
+        );
+
+        transfer.amount = 2_000_000;
+        assert_eq!(
+            bridge.validate_transfer(&transfer).await,
+            Err(TransferValidationError::AboveMax { amount: 2_000_000, max: 1_000_000 })
+        );
+
+        let mut transfer = transfer_to(EVM_RECEIVER);
+        transfer.deadline = 1;
+        assert_eq!(
+            bridge.validate_transfer(&transfer).await,
+            Err(TransferValidationError::Expired { deadline: 1 })
+        );
+
+        let err = bridge.validate_transfer(&transfer_to("bob.near")).await.unwrap_err();
+        assert!(matches!(err, TransferValidationError::InvalidDestination(_)));
+        assert_eq!(err.to_string(), "Invalid ethereum address: bob.near");
     }
 }
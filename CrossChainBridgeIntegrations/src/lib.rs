@@ -1,23 +1,6 @@
-use anyhow::{Context, Result};
-use dashmap::DashMap;
-use futures::{stream, StreamExt};
-use lru::LruCache;
-use metrics::{counter, gauge};
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
-use parking_lot::RwLock;
-use rust_decimal::Decimal;
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Semaphore;
-use tokio_stream::wrappers::ReceiverStream;
-use tracing::{info, warn, error};
 
-// Constants
-const MAX_BATCH_SIZE: usize = 50;
-const MAX_CONCURRENT_TRANSFERS: usize = 10;
-const CACHE_TTL: Duration = Duration::from_secs(60);
-const MAX_RETRIES: u32 = 3;
+pub mod bridge;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BridgeConfig {
@@ -18,6 +18,32 @@ pub struct ProtocolMetrics {
     pub total_supplied: EthersU256,
 }
 
+// Protocols quote rates in their own units: Aave a per-second rate in ray
+// (1e27), Compound a per-block rate scaled by 1e18. Both are annualized to an
+// APY fraction, compounding once per second or once per block, so they compare
+// directly with the other protocols' metrics.
+pub const SECONDS_PER_YEAR: f64 = 31_536_000.0;
+pub const ETH_BLOCKS_PER_YEAR: f64 = 2_628_000.0; // 12 second blocks
+const RAY: f64 = 1e27;
+const WAD: f64 = 1e18;
+
+pub fn annualize_rate(rate_per_period: f64, periods_per_year: f64) -> f64 {
+    // (1 + r)^n - 1 without losing tiny per-period rates to rounding
+    (periods_per_year * rate_per_period.ln_1p()).exp_m1()
+}
+
+pub fn apy_from_ray_per_second(rate: U256) -> f64 {
+    annualize_rate(u256_to_f64(rate) / RAY, SECONDS_PER_YEAR)
+}
+
+pub fn apy_from_per_block_rate(rate: U256, blocks_per_year: f64) -> f64 {
+    annualize_rate(u256_to_f64(rate) / WAD, blocks_per_year)
+}
+
+fn u256_to_f64(value: U256) -> f64 {
+    value.0.iter().rev().fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SwapParams {
     pub token_in: EthersAddress,
@@ -224,12 +250,16 @@ impl DeFiProtocol for AaveProtocol {
             Arc::new(self.provider.clone())
         );
 
-        let (tvl, apy, utilization_rate, total_borrowed, total_supplied) = contract
+        let (tvl, liquidity_rate, utilization_raw, total_borrowed, total_supplied): (U256, U256, U256, U256, U256) = contract
             .method("getReserveData", token)?
             .call()
             .await
             .map_err(|e| CrossChainError::ContractError(e.to_string()))?;
 
+        // Aave's rate is per second in ray; bring it onto the annual basis
+        let apy = apy_from_ray_per_second(liquidity_rate);
+        let utilization_rate = utilization_raw.as_u64() as f64 / 1e4;
+
         Ok(ProtocolMetrics {
             tvl,
             apy,
@@ -358,4 +388,30 @@ mod tests {
         let aave = AaveProtocol::new(rpc_url, address);
         assert!(aave.is_ok());
     }
+
+    #[test]
+    fn test_rates_normalize_to_same_apy() {
+        // Native quotes for a 5% APY
+        let per_second = 1.05f64.ln() / SECONDS_PER_YEAR;
+        let per_block = 1.05f64.ln() / ETH_BLOCKS_PER_YEAR;
+        let aave_ray = U256::from((per_second.exp_m1() * 1e27) as u128);
+        let compound_mantissa = U256::from((per_block.exp_m1() * 1e18) as u128);
+
+        let aave_apy = apy_from_ray_per_second(aave_ray);
+        let compound_apy = apy_from_per_block_rate(compound_mantissa, ETH_BLOCKS_PER_YEAR);
+
+        assert!((aave_apy - 0.05).abs() < 1e-9);
+        assert!((compound_apy - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compounding_exceeds_simple_rate() {
+        // 10% APR compounded per block yields e^0.1 - 1
+        let per_block = U256::from((0.1 / ETH_BLOCKS_PER_YEAR * 1e18) as u128);
+        let apy = apy_from_per_block_rate(per_block, ETH_BLOCKS_PER_YEAR);
+        assert!((apy - 0.1f64.exp_m1()).abs() < 1e-6);
+        assert!(apy > 0.1);
+
+        assert_eq!(apy_from_ray_per_second(U256::zero()), 0.0);
+    }
 }
\ No newline at end of file
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
+use near_contract_standards::fungible_token::Balance;
 use rust_decimal::Decimal;
-use super::analytics::{PoolMetrics, APYBreakdown, RiskScore, VolatilityMetrics, EnhancedPoolMetrics, VolatilityRegime, Signal};
+use rust_decimal::prelude::ToPrimitive;
+use super::analytics::{PoolMetrics, PoolType, TokenShare, SecurityEvent, EnhancedPoolMetrics, Signal, SignalType, calculate_percent_b, resample, ResampleMode, balance_return};
+use super::time::{Seconds, SECONDS_PER_HOUR, SECONDS_PER_DAY, SECONDS_PER_WEEK};
 use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AIModelInput {
@@ -95,10 +100,7 @@ impl PoolFeatures {
             volume_to_tvl_ratio: if tvl > 0.0 { volume / tvl } else { 0.0 },
             liquidity_depth: calculate_liquidity_depth(metrics),
             token_correlation: metrics.impermanent_loss_risk.price_correlation.to_f64().unwrap_or(0.0),
-            pool_age_days: ((metrics.creation_timestamp - std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs()) / 86400) as u32,
+            pool_age_days: calculate_pool_age(metrics),
             pool_type_encoding: encode_pool_type(&metrics.pool_type),
             platform_encoding: encode_platform(&metrics.platform),
             chain_encoding: encode_chain(&metrics.chain),
@@ -112,8 +114,8 @@ impl MarketFeatures {
             price_volatility_1d: metrics.market_volatility.daily_volatility.to_f64().unwrap_or(0.0),
             price_volatility_7d: metrics.market_volatility.weekly_volatility.to_f64().unwrap_or(0.0),
             price_volatility_30d: metrics.market_volatility.monthly_volatility.to_f64().unwrap_or(0.0),
-            volume_trend: calculate_volume_trend(&metrics.performance_history.volume_history),
-            tvl_trend: calculate_tvl_trend(&metrics.performance_history.tvl_history),
+            volume_trend: calculate_relative_change(&metrics.performance_history.volume_history),
+            tvl_trend: calculate_relative_change(&metrics.performance_history.tvl_history),
             market_correlation: calculate_market_correlation(metrics),
             token_dominance: calculate_token_dominance(&metrics.token_distribution),
         }
@@ -144,6 +146,23 @@ impl TemporalFeatures {
 }
 
 // Helper functions
+fn unix_now() -> Seconds {
+    Seconds(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0))
+}
+
+// Whole days between creation and `now`; zero for a creation time in the future
+pub fn pool_age_days(creation_timestamp: u64, now: Seconds) -> u32 {
+    let created = Seconds::from_timestamp(creation_timestamp);
+    now.saturating_sub(created).whole_days().min(u32::MAX as u64) as u32
+}
+
+fn calculate_pool_age(metrics: &PoolMetrics) -> u32 {
+    pool_age_days(metrics.creation_timestamp, unix_now())
+}
+
 fn normalize_tvl(tvl: f64) -> f64 {
     // Log normalization with scaling
     if tvl <= 0.0 {
@@ -169,19 +188,80 @@ fn encode_pool_type(pool_type: &PoolType) -> Vec<f64> {
     ]
 }
 
-fn calculate_volume_trend(history: &[(u64, Balance)]) -> f64 {
+const KNOWN_PLATFORMS: [&str; 5] = ["ref finance", "jumbo", "trisolaris", "burrow", "meta pool"];
+const KNOWN_CHAINS: [&str; 7] = ["near", "aurora", "bsc", "polygon", "avalanche", "solana", "arbitrum"];
+
+// One-hot over `known` plus a trailing "other" slot, so the width never changes
+fn encode_one_hot(value: &str, known: &[&str]) -> Vec<f64> {
+    let value = value.to_lowercase();
+    let mut encoding = vec![0.0; known.len() + 1];
+    let slot = known.iter().position(|k| *k == value).unwrap_or(known.len());
+    encoding[slot] = 1.0;
+    encoding
+}
+
+fn encode_platform(platform: &str) -> Vec<f64> {
+    encode_one_hot(platform, &KNOWN_PLATFORMS)
+}
+
+fn encode_chain(chain: &str) -> Vec<f64> {
+    encode_one_hot(chain, &KNOWN_CHAINS)
+}
+
+// Audit gaps, contract and centralization risk averaged to 0-1; insurance
+// takes a fifth off
+fn calculate_smart_contract_risk(metrics: &PoolMetrics) -> f64 {
+    let security = &metrics.security_score;
+    let unaudited = 100.0 - security.audit_score.min(100) as f64;
+    let risk = (unaudited + security.contract_risk as f64 + security.centralization_risk as f64) / 300.0;
+    if security.insurance_coverage { risk * 0.8 } else { risk }
+}
+
+// Count of past exploits per severity: low, medium, high, critical
+fn encode_risk_events(events: &[SecurityEvent]) -> Vec<f64> {
+    let mut counts = vec![0.0; 4];
+    for event in events {
+        let slot = match event.severity.to_lowercase().as_str() {
+            "low" => 0,
+            "medium" => 1,
+            "high" => 2,
+            _ => 3,
+        };
+        counts[slot] += 1.0;
+    }
+    counts
+}
+
+impl PerformanceMetrics {
+    pub fn from(metrics: &PoolMetrics) -> Self {
+        let enhanced = EnhancedPerformanceMetrics::from(&EnhancedPoolMetrics::new(metrics.clone()));
+        let returns = &metrics.performance_history.daily_returns;
+        let winning = returns.iter().filter(|(_, r)| *r > Decimal::ZERO).count();
+
+        Self {
+            realized_apy: enhanced.realized_apy,
+            sharpe_ratio: enhanced.sharpe_ratio,
+            sortino_ratio: enhanced.sortino_ratio,
+            max_drawdown: enhanced.max_drawdown,
+            success_rate: if returns.is_empty() { 0.0 } else { winning as f64 / returns.len() as f64 },
+        }
+    }
+}
+
+// Change from the first to the last entry, computed in Decimal and converted
+// to f64 only once clamped
+fn calculate_relative_change(history: &[(u64, Balance)]) -> f64 {
     if history.len() < 2 {
         return 0.0;
     }
     
-    let recent = history.last().unwrap().1 as f64;
-    let old = history.first().unwrap().1 as f64;
+    let old = history.first().unwrap().1;
+    let recent = history.last().unwrap().1;
     
-    if old == 0.0 {
-        0.0
-    } else {
-        ((recent - old) / old).min(5.0).max(-5.0)
-    }
+    balance_return(old, recent)
+        .map(|change| change.clamp(Decimal::from(-5), Decimal::from(5)))
+        .and_then(|change| change.to_f64())
+        .unwrap_or(0.0)
 }
 
 fn calculate_market_correlation(metrics: &PoolMetrics) -> f64 {
@@ -242,17 +322,27 @@ fn calculate_seasonality(metrics: &PoolMetrics) -> Vec<f64> {
     // Calculate daily and weekly patterns
     let mut seasonality = vec![0.0; 24 + 7]; // 24 hours + 7 days
     
-    // Process volume history for patterns
-    for (timestamp, volume) in &metrics.performance_history.volume_history {
-        let hour = (timestamp % 86400) / 3600;
-        let day = (timestamp % 604800) / 86400;
+    // Process volume history for patterns, on an hourly grid so densely
+    // sampled periods don't dominate the buckets. Timestamps are brought to
+    // seconds first, otherwise the grid would step in nanoseconds
+    let volumes: Vec<(u64, f64)> = metrics.performance_history.volume_history
+        .iter()
+        .map(|(timestamp, volume)| (Seconds::from_timestamp(*timestamp).0, *volume as f64))
+        .collect();
+    for (timestamp, volume) in resample(&volumes, SECONDS_PER_HOUR, ResampleMode::ForwardFill) {
+        let hour = ((timestamp % SECONDS_PER_DAY) / SECONDS_PER_HOUR) as usize;
+        let day = ((timestamp % SECONDS_PER_WEEK) / SECONDS_PER_DAY) as usize;
         
-        seasonality[hour as usize] += *volume as f64;
-        seasonality[24 + day as usize] += *volume as f64;
+        if let Some(bucket) = seasonality.get_mut(hour) {
+            *bucket += volume;
+        }
+        if let Some(bucket) = seasonality.get_mut(24 + day) {
+            *bucket += volume;
+        }
     }
     
     // Normalize
-    let max_value = seasonality.iter().fold(0.0, |a, &b| a.max(b));
+    let max_value = seasonality.iter().fold(0.0_f64, |a, &b| a.max(b));
     if max_value > 0.0 {
         seasonality.iter_mut().for_each(|v| *v /= max_value);
     }
@@ -279,13 +369,18 @@ fn calculate_trend_indicators(metrics: &PoolMetrics) -> Vec<f64> {
     indicators
 }
 
-fn calculate_trend<T: Into<f64> + Copy>(history: &[(u64, T)]) -> Option<f64> {
+// Least-squares slope of the series relative to its first value, so large
+// balances don't lose precision in the cast to f64
+fn calculate_trend(history: &[(u64, Balance)]) -> Option<f64> {
     if history.len() < 2 {
         return None;
     }
     
+    let base = history[0].1;
     let x: Vec<f64> = (0..history.len()).map(|i| i as f64).collect();
-    let y: Vec<f64> = history.iter().map(|(_, v)| (*v).into()).collect();
+    let y: Vec<f64> = history.iter()
+        .map(|(_, v)| balance_return(base, *v).and_then(|r| r.to_f64()))
+        .collect::<Option<_>>()?;
     
     let n = x.len() as f64;
     let sum_x: f64 = x.iter().sum();
@@ -313,6 +408,9 @@ pub struct EnhancedAIModelInput {
     
     // Target Variables
     pub performance_metrics: EnhancedPerformanceMetrics,
+
+    // Per-feature reliability: 1.0 when computed from enough history, 0.0 when defaulted
+    pub feature_confidence: HashMap<String, f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -413,6 +511,51 @@ pub struct EnhancedPerformanceMetrics {
     pub expected_shortfall: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnhancedRiskFeatures {
+    pub base: RiskFeatures,
+    pub value_at_risk: f64,
+    pub max_drawdown: f64,
+    pub beta: f64,
+    pub rebalancing_threshold: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnhancedTemporalFeatures {
+    pub base: TemporalFeatures,
+    pub historical_volatility: Vec<f64>,
+    pub apy_history: Vec<f64>,
+}
+
+impl EnhancedRiskFeatures {
+    pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
+        let advanced = &metrics.advanced_metrics;
+        Self {
+            base: RiskFeatures::from(&metrics.base_metrics),
+            value_at_risk: advanced.value_at_risk.abs().to_f64().unwrap_or(0.0),
+            max_drawdown: advanced.max_drawdown.abs().to_f64().unwrap_or(0.0),
+            beta: advanced.beta_coefficient.to_f64().unwrap_or(1.0),
+            rebalancing_threshold: metrics.optimization_metrics.rebalancing_threshold.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+impl EnhancedTemporalFeatures {
+    pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
+        Self {
+            base: TemporalFeatures::from(&metrics.base_metrics),
+            historical_volatility: metrics.ml_features.volatility_indicators.historical_volatility
+                .iter()
+                .map(|(_, v)| v.to_f64().unwrap_or(0.0))
+                .collect(),
+            apy_history: metrics.base_metrics.apy.historical_apy
+                .iter()
+                .map(|(_, apy)| apy.to_f64().unwrap_or(0.0))
+                .collect(),
+        }
+    }
+}
+
 impl From<&EnhancedPoolMetrics> for EnhancedAIModelInput {
     fn from(metrics: &EnhancedPoolMetrics) -> Self {
         Self {
@@ -425,8 +568,89 @@ impl From<&EnhancedPoolMetrics> for EnhancedAIModelInput {
             performance_metrics: EnhancedPerformanceMetrics::from(metrics),
             risk_features: EnhancedRiskFeatures::from(metrics),
             temporal_features: EnhancedTemporalFeatures::from(metrics),
+            feature_confidence: calculate_feature_confidence(metrics),
+        }
+    }
+}
+
+impl EnhancedAIModelInput {
+    // Flatten every numeric feature into a named column for tabular training data.
+    // Columns are `section.field`, sorted by name within each section. Encoding
+    // vectors (`*_encoding`) expand into indexed columns; variable-length series
+    // and strings are left out so every input produces the same columns.
+    pub fn to_feature_row(&self) -> Vec<(String, f64)> {
+        let mut row = Vec::new();
+        let value = serde_json::to_value(self).unwrap_or(serde_json::Value::Null);
+        flatten_features("", &value, &mut row);
+        row
+    }
+}
+
+fn flatten_features(prefix: &str, value: &serde_json::Value, row: &mut Vec<(String, f64)>) {
+    match value {
+        serde_json::Value::Number(n) => row.push((prefix.to_string(), n.as_f64().unwrap_or(0.0))),
+        serde_json::Value::Bool(b) => row.push((prefix.to_string(), if *b { 1.0 } else { 0.0 })),
+        serde_json::Value::Object(fields) => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            for key in keys {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_features(&name, &fields[key], row);
+            }
+        }
+        serde_json::Value::Array(items) if prefix.ends_with("_encoding") => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_features(&format!("{}[{}]", prefix, i), item, row);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Write feature rows as CSV with a header taken from the first row.
+// All rows must share the same columns.
+pub fn write_csv(rows: &[Vec<(String, f64)>], path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+    let header: Vec<&str> = match rows.first() {
+        Some(row) => row.iter().map(|(name, _)| name.as_str()).collect(),
+        None => return Ok(()),
+    };
+    writeln!(file, "{}", header.join(","))?;
+
+    for row in rows {
+        if row.len() != header.len() || row.iter().zip(&header).any(|((name, _), h)| name != h) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "feature rows have different columns"));
         }
+        let values: Vec<String> = row.iter().map(|(_, v)| v.to_string()).collect();
+        writeln!(file, "{}", values.join(","))?;
     }
+
+    file.flush()
+}
+
+// Fewer points than this and history-derived features fall back to defaults
+const MIN_HISTORY_POINTS: usize = 2;
+
+fn history_confidence(points: usize) -> f64 {
+    if points < MIN_HISTORY_POINTS { 0.0 } else { 1.0 }
+}
+
+fn calculate_feature_confidence(metrics: &EnhancedPoolMetrics) -> HashMap<String, f64> {
+    let history = &metrics.base_metrics.performance_history;
+    let returns = history_confidence(history.daily_returns.len());
+    let share_prices = history_confidence(history.share_price_history.len());
+
+    let mut confidence = HashMap::new();
+    confidence.insert("volume_trend".to_string(), history_confidence(history.volume_history.len()));
+    confidence.insert("tvl_trend".to_string(), history_confidence(history.tvl_history.len()));
+    confidence.insert("token_correlation".to_string(), returns);
+    confidence.insert("trend_strength".to_string(), returns);
+    confidence.insert("sharpe_ratio".to_string(), returns);
+    confidence.insert("sortino_ratio".to_string(), returns);
+    confidence.insert("var_95".to_string(), returns);
+    confidence.insert("max_drawdown".to_string(), share_prices);
+    confidence.insert("bollinger_signals".to_string(), share_prices);
+    confidence
 }
 
 impl EnhancedPoolFeatures {
@@ -451,6 +675,25 @@ impl EnhancedPoolFeatures {
     }
 }
 
+fn calculate_volume_tvl_ratio(metrics: &PoolMetrics) -> f64 {
+    let tvl = metrics.tvl as f64;
+    if tvl > 0.0 { metrics.volume_24h as f64 / tvl } else { 0.0 }
+}
+
+fn calculate_token_weights(tokens: &[TokenShare]) -> Vec<f64> {
+    tokens.iter().map(|t| t.weight.to_f64().unwrap_or(0.0)).collect()
+}
+
+// 1 - Herfindahl index of token weights: 0 for a single token, higher as
+// value spreads across more tokens
+fn calculate_composition_score(metrics: &PoolMetrics) -> f64 {
+    let weights = calculate_token_weights(&metrics.token_distribution);
+    if weights.is_empty() {
+        return 0.0;
+    }
+    1.0 - weights.iter().map(|w| w * w).sum::<f64>()
+}
+
 // Advanced calculation methods
 fn calculate_capital_efficiency(metrics: &EnhancedPoolMetrics) -> f64 {
     let volume = metrics.base_metrics.volume_24h as f64;
@@ -476,6 +719,129 @@ fn calculate_protocol_dominance(metrics: &PoolMetrics) -> f64 {
     (total_tvl / platform_tvl).min(1.0)
 }
 
+impl EnhancedMarketFeatures {
+    pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
+        let base = MarketFeatures::from(&metrics.base_metrics);
+        let indicators = &metrics.ml_features.market_indicators;
+
+        Self {
+            price_volatility_1d: base.price_volatility_1d,
+            price_volatility_7d: base.price_volatility_7d,
+            price_volatility_30d: base.price_volatility_30d,
+            volume_trend: base.volume_trend,
+            tvl_trend: base.tvl_trend,
+            market_correlation: base.market_correlation,
+            token_dominance: base.token_dominance,
+            market_regime: format!("{:?}", metrics.ml_features.volatility_indicators.volatility_regime),
+            liquidity_score: indicators.liquidity_score.to_f64().unwrap_or(0.0),
+            market_impact: indicators.market_impact.to_f64().unwrap_or(0.0),
+            bid_ask_spread: indicators.bid_ask_spread.to_f64().unwrap_or(0.0),
+            depth_analysis: MarketDepthAnalysis::from(&metrics.base_metrics),
+        }
+    }
+}
+
+impl MarketDepthAnalysis {
+    // AMM pools have no order book, so depth is the trade size (USD) that moves
+    // the price by each step, assuming impact grows linearly from the $10k quote
+    pub fn from(metrics: &PoolMetrics) -> Self {
+        let impact_10k = metrics.market_volatility.price_impact_10000usd.to_f64().unwrap_or(0.0);
+        let depth_at = |move_pct: f64| if impact_10k > 0.0 { 10_000.0 * move_pct / impact_10k } else { 0.0 };
+        let weights = calculate_token_weights(&metrics.token_distribution);
+        let imbalance = weights.iter().cloned().fold(0.0_f64, f64::max) - weights.iter().cloned().fold(1.0_f64, f64::min);
+
+        Self {
+            depth_2pct: depth_at(0.02),
+            depth_5pct: depth_at(0.05),
+            depth_10pct: depth_at(0.10),
+            slippage_impact: impact_10k,
+            order_book_imbalance: if weights.is_empty() { 0.0 } else { imbalance },
+        }
+    }
+}
+
+impl MarketSentiment {
+    pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
+        let sentiment = &metrics.ml_features.sentiment_metrics;
+        Self {
+            social_volume: sentiment.social_volume as f64,
+            sentiment_score: sentiment.sentiment_score.to_f64().unwrap_or(0.5),
+            developer_activity: sentiment.developer_activity as f64,
+            governance_participation: sentiment.governance_participation.to_f64().unwrap_or(0.0),
+            market_fear_greed: metrics.ml_features.momentum_indicators.rsi_14.to_f64().unwrap_or(50.0) / 100.0,
+            whale_activity: metrics.base_metrics.user_metrics.user_concentration.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+impl CrossChainMetrics {
+    // A pool lives on one chain, so it holds all of its own TVL and volume there.
+    // Per-chain gas efficiency stands in for bridge efficiency until bridge
+    // telemetry is available.
+    pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
+        let base = &metrics.base_metrics;
+        let chain = base.chain.to_lowercase();
+        let gas = &base.gas_metrics;
+        let chains = [
+            ("near", &gas.near), ("aurora", &gas.aurora), ("bsc", &gas.bsc), ("polygon", &gas.polygon),
+            ("avalanche", &gas.avalanche), ("solana", &gas.solana), ("arbitrum", &gas.arbitrum),
+        ];
+        let apy = base.apy.total_apy.to_f64().unwrap_or(0.0);
+
+        let mut bridge_efficiency = HashMap::new();
+        let mut gas_adjusted_returns = HashMap::new();
+        for (name, metrics) in chains {
+            let efficiency = metrics.gas_efficiency_score.min(100) as f64 / 100.0;
+            bridge_efficiency.insert(name.to_string(), efficiency);
+            gas_adjusted_returns.insert(name.to_string(), apy * efficiency);
+        }
+
+        Self {
+            chain_tvl_share: HashMap::from([(chain.clone(), 1.0)]),
+            cross_chain_volume: HashMap::from([(chain, base.volume_24h as f64)]),
+            bridge_efficiency,
+            gas_adjusted_returns,
+            chain_correlation: vec![vec![1.0]],
+        }
+    }
+}
+
+impl EnhancedPerformanceMetrics {
+    pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
+        let advanced = &metrics.advanced_metrics;
+        let returns: Vec<f64> = metrics.base_metrics.performance_history.daily_returns
+            .iter()
+            .map(|(_, r)| r.to_f64().unwrap_or(0.0))
+            .collect();
+        let gains: f64 = returns.iter().filter(|r| **r > 0.0).sum();
+        let losses: f64 = returns.iter().filter(|r| **r < 0.0).map(|r| r.abs()).sum();
+        let wins = returns.iter().filter(|r| **r > 0.0).count();
+        let loss_count = returns.iter().filter(|r| **r < 0.0).count();
+
+        // Losses are reported as positive magnitudes
+        let var_95 = advanced.value_at_risk.abs().to_f64().unwrap_or(0.0);
+        let tail: Vec<f64> = returns.iter().filter(|r| -**r >= var_95 && **r <= 0.0).cloned().collect();
+        let expected_shortfall = if tail.is_empty() { var_95 } else { -tail.iter().sum::<f64>() / tail.len() as f64 };
+        let max_drawdown = advanced.max_drawdown.abs().to_f64().unwrap_or(0.0);
+        let total_return = returns.iter().fold(1.0, |wealth, r| wealth * (1.0 + r)) - 1.0;
+
+        Self {
+            realized_apy: metrics.base_metrics.apy.total_apy.to_f64().unwrap_or(0.0),
+            risk_adjusted_return: metrics.base_metrics.calculate_risk_adjusted_apy().to_f64().unwrap_or(0.0),
+            sharpe_ratio: advanced.sharpe_ratio.to_f64().unwrap_or(0.0),
+            sortino_ratio: advanced.sortino_ratio.to_f64().unwrap_or(0.0),
+            max_drawdown,
+            recovery_factor: if max_drawdown > 0.0 { total_return / max_drawdown } else { 0.0 },
+            win_loss_ratio: if loss_count > 0 { wins as f64 / loss_count as f64 } else { wins as f64 },
+            profit_factor: if losses > 0.0 { gains / losses } else { 0.0 },
+            calmar_ratio: advanced.calmar_ratio.to_f64().unwrap_or(0.0),
+            omega_ratio: advanced.omega_ratio.to_f64().unwrap_or(0.0),
+            var_95,
+            expected_shortfall,
+        }
+    }
+}
+
 impl TechnicalIndicators {
     pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
         let momentum = &metrics.ml_features.momentum_indicators;
@@ -486,7 +852,10 @@ impl TechnicalIndicators {
                 momentum.macd.0.to_f64().unwrap_or(0.0),
                 momentum.macd.1.to_f64().unwrap_or(0.0),
             ],
-            bollinger_signals: calculate_bollinger_signals(&metrics.ml_features.volatility_indicators.bollinger_bands),
+            bollinger_signals: calculate_bollinger_signals(
+                &metrics.ml_features.volatility_indicators.bollinger_bands,
+                metrics.base_metrics.performance_history.share_price_history.last().map(|&(_, price)| price),
+            ),
             momentum_signals: vec![momentum.momentum_score.to_f64().unwrap_or(0.0)],
             trend_strength: calculate_trend_strength(metrics),
             support_resistance: calculate_support_resistance(metrics),
@@ -495,12 +864,11 @@ impl TechnicalIndicators {
     }
 }
 
-fn calculate_bollinger_signals(bands: &(Decimal, Decimal, Decimal)) -> Vec<f64> {
-    vec![
-        bands.0.to_f64().unwrap_or(0.0),  // Upper band
-        bands.1.to_f64().unwrap_or(0.0),  // Middle band
-        bands.2.to_f64().unwrap_or(0.0),  // Lower band
-    ]
+// %B of the latest share price against the bands; empty without a price
+fn calculate_bollinger_signals(bands: &(Decimal, Decimal, Decimal), latest_price: Option<Decimal>) -> Vec<f64> {
+    latest_price
+        .map(|price| vec![calculate_percent_b(price, bands).to_f64().unwrap_or(0.5)])
+        .unwrap_or_default()
 }
 
 fn calculate_trend_strength(metrics: &EnhancedPoolMetrics) -> f64 {
@@ -526,6 +894,11 @@ fn calculate_trend_strength(metrics: &EnhancedPoolMetrics) -> f64 {
     }
 }
 
+// No close-price series yet to place levels on
+fn calculate_support_resistance(_metrics: &EnhancedPoolMetrics) -> Vec<f64> {
+    Vec::new()
+}
+
 impl OptimizationFeatures {
     pub fn from(metrics: &EnhancedPoolMetrics) -> Self {
         Self {
@@ -547,6 +920,31 @@ fn extract_rebalance_signals(signals: &[Signal]) -> Vec<f64> {
         .collect()
 }
 
+fn extract_entry_points(signals: &[Signal]) -> Vec<f64> {
+    signals.iter()
+        .filter(|s| matches!(s.signal_type, SignalType::Entry))
+        .map(|s| s.timestamp as f64)
+        .collect()
+}
+
+fn extract_exit_points(signals: &[Signal]) -> Vec<f64> {
+    signals.iter()
+        .filter(|s| matches!(s.signal_type, SignalType::Exit | SignalType::RiskWarning))
+        .map(|s| s.timestamp as f64)
+        .collect()
+}
+
+// Risk allocation ordered by token symbol so positions line up across pools
+fn calculate_risk_adjusted_allocation(metrics: &EnhancedPoolMetrics) -> Vec<f64> {
+    let mut allocation: Vec<(&String, &Decimal)> = metrics.optimization_metrics.risk_allocation.iter().collect();
+    allocation.sort_by_key(|(symbol, _)| *symbol);
+    allocation.into_iter().map(|(_, weight)| weight.to_f64().unwrap_or(0.0)).collect()
+}
+
+fn calculate_gas_optimization_score(metrics: &EnhancedPoolMetrics) -> f64 {
+    metrics.base_metrics.gas_metrics.near.gas_efficiency_score.min(100) as f64 / 100.0
+}
+
 fn calculate_timing_efficiency(metrics: &EnhancedPoolMetrics) -> f64 {
     let signals = &metrics.optimization_metrics.entry_signals;
     if signals.is_empty() {
@@ -1,8 +1,9 @@
-use near_sdk::{Balance, AccountId};
+use near_contract_standards::fungible_token::Balance;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::*;
+use super::time::SECONDS_PER_DAY;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PoolMetrics {
@@ -112,7 +113,7 @@ pub struct SecurityEvent {
     pub resolution: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MultiChainGasMetrics {
     pub near: GasMetrics,
     pub aurora: GasMetrics,
@@ -123,7 +124,7 @@ pub struct MultiChainGasMetrics {
     pub arbitrum: GasMetrics,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct GasMetrics {
     pub avg_gas_cost: Balance,
     pub gas_token_price: Decimal,
@@ -166,6 +167,7 @@ pub struct PerformanceHistory {
     pub volume_history: Vec<(u64, Balance)>,
     pub tvl_history: Vec<(u64, Balance)>,
     pub il_history: Vec<(u64, Decimal)>,
+    pub share_price_history: Vec<(u64, Decimal)>,
 }
 
 // Analytics Implementation
@@ -202,9 +204,9 @@ impl PoolMetrics {
 
     pub fn calculate_volatility_impact(&self, amount_usd: Decimal) -> Decimal {
         let base_impact = if amount_usd <= Decimal::from(1000) {
-            self.volatility_metrics.price_impact_1000usd
+            self.market_volatility.price_impact_1000usd
         } else {
-            self.volatility_metrics.price_impact_10000usd
+            self.market_volatility.price_impact_10000usd
         };
 
         base_impact * (amount_usd / Decimal::from(1000)).sqrt().unwrap_or(Decimal::ONE)
@@ -234,7 +236,7 @@ pub fn calculate_correlation(x: &[Decimal], y: &[Decimal]) -> Decimal {
     }
 }
 
-pub fn calculate_volatility(prices: &[(u64, Decimal)], window: u64) -> Decimal {
+pub fn calculate_volatility(prices: &[(u64, Decimal)], _window: u64) -> Decimal {
     if prices.len() < 2 {
         return Decimal::ZERO;
     }
@@ -251,6 +253,172 @@ pub fn calculate_volatility(prices: &[(u64, Decimal)], window: u64) -> Decimal {
     variance.sqrt().unwrap_or(Decimal::ZERO)
 }
 
+// Largest integer Decimal can hold exactly (96-bit mantissa)
+const DECIMAL_MAX_MANTISSA: u128 = (1 << 96) - 1;
+
+// Balance in whole tokens (24 decimals). Raw yocto amounts above ~79k NEAR
+// don't fit a Decimal, so precision is dropped from the bottom instead.
+pub fn balance_to_decimal(amount: Balance) -> Decimal {
+    let (mut mantissa, mut scale) = (amount, 24);
+    while mantissa > DECIMAL_MAX_MANTISSA {
+        mantissa /= 10;
+        scale -= 1;
+    }
+    Decimal::from_i128_with_scale(mantissa as i128, scale)
+}
+
+// Signed relative change from `old` to `new`. Yocto-scale balances overflow
+// Decimal, so both are scaled down by the same power of ten first, which keeps
+// ~28 significant digits of the ratio. None when `old` is zero.
+pub fn balance_return(old: Balance, new: Balance) -> Option<Decimal> {
+    let (mut old, mut new) = (old, new);
+    while old.max(new) > DECIMAL_MAX_MANTISSA {
+        old /= 10;
+        new /= 10;
+    }
+    if old == 0 {
+        return None;
+    }
+
+    let old = Decimal::from_u128(old)?;
+    let new = Decimal::from_u128(new)?;
+    Some((new - old) / old)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ResampleMode {
+    ForwardFill,  // Carry the last observation forward
+    Linear,       // Interpolate between neighbouring observations
+}
+
+// Resample an irregular series onto a uniform grid starting at the first
+// timestamp and stepping by `interval` up to the last one.
+pub fn resample(series: &[(u64, f64)], interval: u64, mode: ResampleMode) -> Vec<(u64, f64)> {
+    if series.is_empty() || interval == 0 {
+        return series.to_vec();
+    }
+
+    let mut sorted = series.to_vec();
+    sorted.sort_by_key(|(timestamp, _)| *timestamp);
+    let (start, end) = (sorted[0].0, sorted[sorted.len() - 1].0);
+
+    let mut resampled = Vec::new();
+    let mut next = 0; // index of the first observation after the grid point
+    let mut t = start;
+    while t <= end {
+        while next < sorted.len() && sorted[next].0 <= t {
+            next += 1;
+        }
+        let (prev_t, prev_v) = sorted[next - 1];
+
+        let value = match (mode, sorted.get(next)) {
+            (ResampleMode::Linear, Some(&(next_t, next_v))) if prev_t != t => {
+                prev_v + (next_v - prev_v) * (t - prev_t) as f64 / (next_t - prev_t) as f64
+            }
+            _ => prev_v,
+        };
+        resampled.push((t, value));
+        t += interval;
+    }
+
+    resampled
+}
+
+// Relative Strength Index with Wilder smoothing over the latest close.
+// None until there are `period` price changes.
+pub fn calculate_rsi(closes: &[Decimal], period: usize) -> Option<Decimal> {
+    if period == 0 || closes.len() <= period {
+        return None;
+    }
+
+    let changes: Vec<Decimal> = closes.windows(2).map(|w| w[1] - w[0]).collect();
+    let period_dec = Decimal::from(period);
+
+    // Seed with simple averages, then smooth: avg = (prev * (n - 1) + current) / n
+    let mut avg_gain = changes[..period].iter().map(|c| (*c).max(Decimal::ZERO)).sum::<Decimal>() / period_dec;
+    let mut avg_loss = changes[..period].iter().map(|c| (-*c).max(Decimal::ZERO)).sum::<Decimal>() / period_dec;
+    for change in &changes[period..] {
+        avg_gain = (avg_gain * (period_dec - Decimal::ONE) + (*change).max(Decimal::ZERO)) / period_dec;
+        avg_loss = (avg_loss * (period_dec - Decimal::ONE) + (-*change).max(Decimal::ZERO)) / period_dec;
+    }
+
+    if avg_loss == Decimal::ZERO {
+        return Some(Decimal::from(100));
+    }
+    let relative_strength = avg_gain / avg_loss;
+    Some(Decimal::from(100) - Decimal::from(100) / (Decimal::ONE + relative_strength))
+}
+
+// Exponential moving average series, seeded with the simple average of the
+// first `period` values. The first element corresponds to closes[period - 1].
+pub fn calculate_ema(values: &[Decimal], period: usize) -> Vec<Decimal> {
+    if period == 0 || values.len() < period {
+        return Vec::new();
+    }
+
+    let multiplier = Decimal::TWO / Decimal::from(period + 1);
+    let seed = values[..period].iter().sum::<Decimal>() / Decimal::from(period);
+
+    let mut ema = vec![seed];
+    for value in &values[period..] {
+        let previous = *ema.last().unwrap();
+        ema.push((*value - previous) * multiplier + previous);
+    }
+    ema
+}
+
+// MACD (12/26 EMA) and its 9-period signal line at the latest close.
+// None until there are enough closes for the signal line (26 + 9 - 1).
+pub fn calculate_macd(closes: &[Decimal]) -> Option<(Decimal, Decimal)> {
+    const FAST: usize = 12;
+    const SLOW: usize = 26;
+    const SIGNAL: usize = 9;
+
+    if closes.len() < SLOW + SIGNAL - 1 {
+        return None;
+    }
+
+    let fast = calculate_ema(closes, FAST);
+    let slow = calculate_ema(closes, SLOW);
+
+    // Align the fast EMA with the slow one, which starts SLOW - FAST closes later
+    let macd_line: Vec<Decimal> = fast[SLOW - FAST..]
+        .iter()
+        .zip(slow.iter())
+        .map(|(f, s)| *f - *s)
+        .collect();
+    let signal_line = calculate_ema(&macd_line, SIGNAL);
+
+    Some((*macd_line.last()?, *signal_line.last()?))
+}
+
+// Bollinger bands (upper, middle, lower) over the latest `window` prices:
+// SMA ± k · population standard deviation.
+pub fn calculate_bollinger_bands(prices: &[Decimal], window: usize, k: Decimal) -> Option<(Decimal, Decimal, Decimal)> {
+    if window == 0 || prices.len() < window {
+        return None;
+    }
+
+    let recent = &prices[prices.len() - window..];
+    let middle = recent.iter().sum::<Decimal>() / Decimal::from(window);
+    let variance = recent.iter()
+        .map(|p| (*p - middle) * (*p - middle))
+        .sum::<Decimal>() / Decimal::from(window);
+    let width = k * variance.sqrt().unwrap_or(Decimal::ZERO);
+
+    Some((middle + width, middle, middle - width))
+}
+
+// %B = (price - lower) / (upper - lower). Above 1 means the price broke the
+// upper band, below 0 the lower one. Zero-width bands give a neutral 0.5.
+pub fn calculate_percent_b(price: Decimal, bands: &(Decimal, Decimal, Decimal)) -> Decimal {
+    let (upper, _, lower) = *bands;
+    if upper <= lower {
+        return Decimal::new(5, 1);
+    }
+    (price - lower) / (upper - lower)
+}
+
 // Advanced Analytics Enhancements
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EnhancedPoolMetrics {
@@ -348,28 +516,121 @@ pub enum SignalType {
     RiskWarning,
 }
 
+// Point-in-time headline metrics, small enough to persist periodically for dashboards
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub timestamp: u64,
+    pub apy: Decimal,
+    pub risk_score: u32,
+    pub sharpe_ratio: Decimal,
+}
+
+// Change from one snapshot to a later one (later minus earlier)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MetricsDelta {
+    pub elapsed: i64,
+    pub apy_change: Decimal,
+    pub risk_score_change: i64,
+    pub sharpe_change: Decimal,
+}
+
+impl MetricsSnapshot {
+    pub fn diff(&self, other: &MetricsSnapshot) -> MetricsDelta {
+        MetricsDelta {
+            elapsed: other.timestamp as i64 - self.timestamp as i64,
+            apy_change: other.apy - self.apy,
+            risk_score_change: other.risk_score as i64 - self.risk_score as i64,
+            sharpe_change: other.sharpe_ratio - self.sharpe_ratio,
+        }
+    }
+}
+
+// Benchmark used for excess-return metrics. Rates are per period of the
+// analyzed return series.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchmarkConfig {
+    pub market_return: Decimal,
+    pub risk_free_rate: Decimal,
+    pub market_returns: Vec<Decimal>,  // Market series aligned with the pool's returns, for beta
+    pub minimum_acceptable_return: Decimal,  // Sortino MAR; returns below it count as downside
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            market_return: Decimal::new(8, 2),  // 8%
+            risk_free_rate: Decimal::new(2, 2), // 2%
+            market_returns: Vec::new(),
+            minimum_acceptable_return: Decimal::ZERO,
+        }
+    }
+}
+
 // Enhanced Implementation
 impl EnhancedPoolMetrics {
     pub fn new(base_metrics: PoolMetrics) -> Self {
+        Self::new_with_benchmark(base_metrics, &BenchmarkConfig::default())
+    }
+
+    pub fn new_with_benchmark(base_metrics: PoolMetrics, benchmark: &BenchmarkConfig) -> Self {
         Self {
             base_metrics: base_metrics.clone(),
-            advanced_metrics: Self::calculate_advanced_metrics(&base_metrics),
+            advanced_metrics: Self::calculate_advanced_metrics(&base_metrics, benchmark),
             ml_features: Self::generate_ml_features(&base_metrics),
             optimization_metrics: Self::compute_optimization_metrics(&base_metrics),
         }
     }
 
-    fn calculate_advanced_metrics(metrics: &PoolMetrics) -> AdvancedMetrics {
+    // Momentum on daily closes resampled from share-price history, since RSI and
+    // MACD assume evenly spaced observations
+    fn calculate_momentum_indicators(metrics: &PoolMetrics) -> MomentumIndicators {
+        const ROC_PERIOD: usize = 14;
+
+        let prices: Vec<(u64, f64)> = metrics.performance_history.share_price_history
+            .iter()
+            .map(|(timestamp, price)| (*timestamp, price.to_f64().unwrap_or(0.0)))
+            .collect();
+        let closes: Vec<Decimal> = resample(&prices, SECONDS_PER_DAY, ResampleMode::ForwardFill)
+            .into_iter()
+            .map(|(_, price)| Decimal::from_f64(price).unwrap_or(Decimal::ZERO))
+            .collect();
+
+        let rsi_14 = calculate_rsi(&closes, 14).unwrap_or(Decimal::from(50));
+        let macd = calculate_macd(&closes).unwrap_or((Decimal::ZERO, Decimal::ZERO));
+        let rate_of_change = match closes.len().checked_sub(ROC_PERIOD + 1) {
+            Some(start) if closes[start] != Decimal::ZERO => {
+                (closes[closes.len() - 1] - closes[start]) / closes[start]
+            }
+            _ => Decimal::ZERO,
+        };
+
+        MomentumIndicators {
+            rsi_14,
+            macd,
+            rate_of_change,
+            momentum_score: rsi_14 / Decimal::from(100),
+        }
+    }
+
+    pub fn snapshot(&self, timestamp: u64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            timestamp,
+            apy: self.base_metrics.apy.total_apy,
+            risk_score: self.base_metrics.impermanent_loss_risk.score,
+            sharpe_ratio: self.advanced_metrics.sharpe_ratio,
+        }
+    }
+
+    fn calculate_advanced_metrics(metrics: &PoolMetrics, benchmark: &BenchmarkConfig) -> AdvancedMetrics {
         let returns = Self::calculate_returns(&metrics.performance_history.daily_returns);
         let volatility = Self::calculate_volatility(&returns);
-        let downside_returns = Self::calculate_downside_returns(&returns);
         
         AdvancedMetrics {
-            alpha_score: Self::calculate_alpha(&returns),
-            beta_coefficient: Self::calculate_beta(&returns),
-            sharpe_ratio: Self::calculate_sharpe_ratio(&returns, &volatility),
-            sortino_ratio: Self::calculate_sortino_ratio(&returns, &downside_returns),
-            max_drawdown: Self::calculate_max_drawdown(&metrics.performance_history.tvl_history),
+            alpha_score: Self::calculate_alpha(&returns, benchmark),
+            beta_coefficient: Self::calculate_beta(&returns, &benchmark.market_returns),
+            sharpe_ratio: Self::calculate_sharpe_ratio(&returns, &volatility, benchmark.risk_free_rate),
+            sortino_ratio: Self::calculate_sortino_ratio(&returns, benchmark.minimum_acceptable_return),
+            max_drawdown: Self::calculate_max_drawdown(&metrics.performance_history.share_price_history),
             value_at_risk: Self::calculate_var(&returns, Decimal::new(95, 2)), // 95% confidence
             calmar_ratio: Self::calculate_calmar_ratio(&returns),
             omega_ratio: Self::calculate_omega_ratio(&returns),
@@ -397,26 +658,67 @@ impl EnhancedPoolMetrics {
     }
 
     // Advanced calculation methods
-    fn calculate_alpha(returns: &[Decimal]) -> Decimal {
+    // Jensen's alpha: return above the CAPM expected return
+    fn calculate_alpha(returns: &[Decimal], benchmark: &BenchmarkConfig) -> Decimal {
         if returns.is_empty() {
             return Decimal::ZERO;
         }
         
-        let market_return = Decimal::new(8, 2); // Assumed market return of 8%
-        let risk_free_rate = Decimal::new(2, 2); // Assumed risk-free rate of 2%
+        let beta = Self::calculate_beta(returns, &benchmark.market_returns);
+        let expected_return = benchmark.risk_free_rate + beta * (benchmark.market_return - benchmark.risk_free_rate);
         
         let avg_return: Decimal = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
-        avg_return - (risk_free_rate + market_return)
+        avg_return - expected_return
     }
 
-    fn calculate_beta(returns: &[Decimal]) -> Decimal {
+    fn calculate_sharpe_ratio(returns: &[Decimal], volatility: &Decimal, risk_free_rate: Decimal) -> Decimal {
+        if returns.is_empty() || *volatility == Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let avg_return: Decimal = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
+        (avg_return - risk_free_rate) / *volatility
+    }
+
+    // Sortino = (mean return - MAR) / downside deviation
+    pub fn calculate_sortino_ratio(returns: &[Decimal], mar: Decimal) -> Decimal {
+        let downside_deviation = Self::calculate_downside_deviation(returns, mar);
+        if downside_deviation == Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let avg_return: Decimal = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
+        (avg_return - mar) / downside_deviation
+    }
+
+    // Root mean square of shortfalls below the MAR. Periods at or above the MAR
+    // contribute zero, and the mean is taken over *all* periods rather than only
+    // the losing ones, so sparse losses aren't overstated.
+    pub fn calculate_downside_deviation(returns: &[Decimal], mar: Decimal) -> Decimal {
         if returns.is_empty() {
+            return Decimal::ZERO;
+        }
+
+        let sum_squared_shortfall = returns.iter()
+            .map(|r| (*r - mar).min(Decimal::ZERO))
+            .map(|shortfall| shortfall * shortfall)
+            .sum::<Decimal>();
+
+        (sum_squared_shortfall / Decimal::from(returns.len())).sqrt().unwrap_or(Decimal::ZERO)
+    }
+
+    // beta = cov(asset, market) / var(market) over the most recent common window.
+    // Defaults to 1.0 without enough market data or when the market is flat.
+    pub fn calculate_beta(returns: &[Decimal], market_returns: &[Decimal]) -> Decimal {
+        let periods = returns.len().min(market_returns.len());
+        if periods < 2 {
             return Decimal::ONE;
         }
 
-        let market_returns = vec![Decimal::new(1, 2); returns.len()]; // Simplified market returns
-        let covariance = Self::calculate_covariance(returns, &market_returns);
-        let market_variance = Self::calculate_variance(&market_returns);
+        let returns = &returns[returns.len() - periods..];
+        let market_returns = &market_returns[market_returns.len() - periods..];
+        let covariance = Self::calculate_covariance(returns, market_returns);
+        let market_variance = Self::calculate_variance(market_returns);
         
         if market_variance == Decimal::ZERO {
             Decimal::ONE
@@ -426,7 +728,7 @@ impl EnhancedPoolMetrics {
     }
 
     fn calculate_optimal_position(metrics: &PoolMetrics) -> Decimal {
-        let tvl = Decimal::from(metrics.tvl);
+        let tvl = balance_to_decimal(metrics.tvl);
         let volatility = metrics.market_volatility.daily_volatility;
         let risk_score = Decimal::from(metrics.impermanent_loss_risk.score);
         
@@ -455,7 +757,7 @@ impl EnhancedPoolMetrics {
 
         // Generate signals based on multiple indicators
         for window in price_history.windows(2) {
-            let (prev_timestamp, prev_return) = window[0];
+            let (_, prev_return) = window[0];
             let (curr_timestamp, curr_return) = window[1];
             
             // Momentum signal
@@ -484,6 +786,176 @@ impl EnhancedPoolMetrics {
         signals
     }
 
+    pub fn calculate_returns(daily_returns: &[(u64, Decimal)]) -> Vec<Decimal> {
+        daily_returns.iter().map(|(_, r)| *r).collect()
+    }
+
+    // Sample standard deviation of the return series
+    pub fn calculate_volatility(returns: &[Decimal]) -> Decimal {
+        if returns.len() < 2 {
+            return Decimal::ZERO;
+        }
+        Self::calculate_variance(returns).sqrt().unwrap_or(Decimal::ZERO)
+    }
+
+    // Largest peak-to-trough decline in share price, as a non-positive fraction
+    // of the peak. Share price is used instead of TVL so deposits and withdrawals
+    // don't register as performance.
+    pub fn calculate_max_drawdown(share_prices: &[(u64, Decimal)]) -> Decimal {
+        let mut peak = Decimal::ZERO;
+        let mut max_drawdown = Decimal::ZERO;
+
+        for (_, price) in share_prices {
+            if *price > peak {
+                peak = *price;
+            } else if peak > Decimal::ZERO {
+                let drawdown = (*price - peak) / peak;
+                max_drawdown = max_drawdown.min(drawdown);
+            }
+        }
+
+        max_drawdown
+    }
+
+    // Historical VaR: the return at the (1 - confidence) quantile, reported as a
+    // non-positive loss like the drawdown
+    pub fn calculate_var(returns: &[Decimal], confidence: Decimal) -> Decimal {
+        if returns.is_empty() {
+            return Decimal::ZERO;
+        }
+
+        let mut sorted = returns.to_vec();
+        sorted.sort();
+        let tail = ((Decimal::ONE - confidence) * Decimal::from(sorted.len()))
+            .floor()
+            .to_usize()
+            .unwrap_or(0)
+            .min(sorted.len() - 1);
+        sorted[tail].min(Decimal::ZERO)
+    }
+
+    // Mean return over the worst peak-to-trough loss of the compounded returns
+    fn calculate_calmar_ratio(returns: &[Decimal]) -> Decimal {
+        let (mut wealth, mut peak) = (Decimal::ONE, Decimal::ONE);
+        let mut max_drawdown = Decimal::ZERO;
+        for r in returns {
+            wealth *= Decimal::ONE + *r;
+            peak = peak.max(wealth);
+            max_drawdown = max_drawdown.max((peak - wealth) / peak);
+        }
+        if max_drawdown == Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let avg_return: Decimal = returns.iter().sum::<Decimal>() / Decimal::from(returns.len());
+        avg_return / max_drawdown
+    }
+
+    // Sum of gains over sum of losses around a zero threshold; zero without losses
+    fn calculate_omega_ratio(returns: &[Decimal]) -> Decimal {
+        let gains: Decimal = returns.iter().filter(|r| **r > Decimal::ZERO).sum();
+        let losses: Decimal = returns.iter().filter(|r| **r < Decimal::ZERO).map(|r| r.abs()).sum();
+        if losses == Decimal::ZERO {
+            Decimal::ZERO
+        } else {
+            gains / losses
+        }
+    }
+
+    fn calculate_volatility_indicators(metrics: &PoolMetrics) -> VolatilityIndicators {
+        const BAND_WINDOW: usize = 20;
+
+        let prices: Vec<Decimal> = metrics.performance_history.share_price_history.iter()
+            .map(|(_, price)| *price)
+            .collect();
+        let bollinger_bands = calculate_bollinger_bands(&prices, BAND_WINDOW.min(prices.len()), Decimal::TWO)
+            .unwrap_or((Decimal::ZERO, Decimal::ZERO, Decimal::ZERO));
+
+        let daily_volatility = metrics.market_volatility.daily_volatility;
+        let volatility_regime = if daily_volatility < Decimal::new(1, 2) {
+            VolatilityRegime::Low
+        } else if daily_volatility < Decimal::new(3, 2) {
+            VolatilityRegime::Medium
+        } else if daily_volatility < Decimal::new(6, 2) {
+            VolatilityRegime::High
+        } else {
+            VolatilityRegime::Extreme
+        };
+
+        VolatilityIndicators {
+            bollinger_bands,
+            average_true_range: Decimal::ZERO,
+            historical_volatility: Vec::new(),
+            volatility_regime,
+        }
+    }
+
+    fn calculate_market_indicators(metrics: &PoolMetrics) -> MarketIndicators {
+        let tvl = balance_to_decimal(metrics.tvl);
+        let ratio_to_tvl = |amount: Balance| {
+            if tvl == Decimal::ZERO { Decimal::ZERO } else { balance_to_decimal(amount) / tvl }
+        };
+        let market_depth = ratio_to_tvl(metrics.liquidity);
+        let market_impact = metrics.market_volatility.price_impact_10000usd;
+
+        MarketIndicators {
+            market_depth,
+            bid_ask_spread: metrics.fee_structure.swap_fee,
+            liquidity_score: (market_depth / (Decimal::ONE + market_impact)).min(Decimal::ONE),
+            market_impact,
+            market_efficiency_coefficient: ratio_to_tvl(metrics.volume_24h).min(Decimal::ONE),
+        }
+    }
+
+    // No off-chain sentiment feed yet; activity comes from the pool's own users
+    fn calculate_sentiment_metrics(metrics: &PoolMetrics) -> SentimentMetrics {
+        SentimentMetrics {
+            social_volume: metrics.user_metrics.active_users_24h as u64,
+            sentiment_score: Decimal::new(5, 1),
+            developer_activity: 0,
+            governance_participation: Decimal::ZERO,
+        }
+    }
+
+    // Drift worth rebalancing: two days of volatility, at least 1%
+    fn calculate_rebalancing_threshold(metrics: &PoolMetrics) -> Decimal {
+        (metrics.market_volatility.daily_volatility * Decimal::TWO).max(Decimal::new(1, 2))
+    }
+
+    fn generate_exit_signals(metrics: &PoolMetrics) -> Vec<Signal> {
+        let mut signals = Vec::new();
+
+        for window in metrics.performance_history.daily_returns.windows(2) {
+            let (_, prev_return) = window[0];
+            let (curr_timestamp, curr_return) = window[1];
+
+            // Accelerating losses
+            if curr_return < prev_return && curr_return < -Decimal::new(5, 2) {
+                signals.push(Signal {
+                    timestamp: curr_timestamp,
+                    signal_type: SignalType::Exit,
+                    strength: curr_return.abs() / Decimal::new(5, 2),
+                    confidence: Decimal::new(80, 2),
+                    indicators: vec!["momentum".to_string(), "stop_loss".to_string()],
+                });
+            }
+        }
+
+        signals
+    }
+
+    // Token weights scaled down by IL risk; the remainder stays unallocated
+    fn calculate_risk_allocation(metrics: &PoolMetrics) -> HashMap<String, Decimal> {
+        let risk = Decimal::from(metrics.impermanent_loss_risk.score.min(100)) / Decimal::from(100);
+        metrics.token_distribution.iter()
+            .map(|token| (token.symbol.clone(), token.weight * (Decimal::ONE - risk)))
+            .collect()
+    }
+
+    fn calculate_opportunity_score(metrics: &PoolMetrics) -> Decimal {
+        metrics.calculate_risk_adjusted_apy() * Decimal::from(metrics.apy.apy_stability_score) / Decimal::from(100)
+    }
+
     // Helper methods for statistical calculations
     fn calculate_covariance(x: &[Decimal], y: &[Decimal]) -> Decimal {
         if x.len() != y.len() || x.is_empty() {
@@ -0,0 +1,54 @@
+// NEP-297 style event logs. Every event carries the correlation id of the
+// call that emitted it, so indexers can group the deposit, fee, allocation
+// and TVL events produced by a single transaction.
+
+use near_sdk::json_types::{I128, U128};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, AccountId};
+
+pub const EVENT_STANDARD: &str = "citadel-vault";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum VaultEvent<'a> {
+    Deposit { account_id: &'a AccountId, amount: U128, shares: U128 },
+    Withdraw { account_id: &'a AccountId, amount: U128, shares: U128 },
+    WithdrawReverted { account_id: &'a AccountId, amount: U128, shares: U128 },
+    TokenDeposit { account_id: &'a AccountId, token: &'a AccountId, amount: U128, shares: U128 },
+    TokenWithdraw { account_id: &'a AccountId, token: &'a AccountId, amount: U128, shares: U128 },
+    TokenWithdrawReverted { account_id: &'a AccountId, token: &'a AccountId, amount: U128, shares: U128 },
+    RewardsClaimed { account_id: &'a AccountId, amount: U128 },
+    RewardsClaimReverted { account_id: &'a AccountId, amount: U128 },
+    FeeCharged { amount: U128 },
+    StrategyAllocation { strategy: &'a str, amount: U128 },
+    Harvest { total_yield: U128 },
+    TvlUpdated { total_value_locked: U128 },
+    AccountingReconciled { previous_total: U128, reconciled_total: U128, drift: I128 },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    correlation_id: &'a str,
+    #[serde(flatten)]
+    event: &'a VaultEvent<'a>,
+}
+
+impl VaultEvent<'_> {
+    pub fn emit(&self, correlation_id: &str) {
+        let log = EventLog { standard: EVENT_STANDARD, version: EVENT_VERSION, correlation_id, event: self };
+        env::log_str(&format!("EVENT_JSON:{}", near_sdk::serde_json::to_string(&log).unwrap()));
+    }
+}
+
+// Hex of the first 16 bytes of sha256(block height, predecessor, counter).
+// The counter is bumped per call, so calls in the same block differ.
+pub fn correlation_id(block_height: u64, predecessor: &AccountId, counter: u64) -> String {
+    let mut preimage = block_height.to_le_bytes().to_vec();
+    preimage.extend_from_slice(predecessor.as_bytes());
+    preimage.extend_from_slice(&counter.to_le_bytes());
+    env::sha256(&preimage)[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
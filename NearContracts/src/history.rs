@@ -0,0 +1,283 @@
+// Tiered retention for timestamped series. Recent points are kept at fine
+// granularity and older ones downsampled to coarser buckets, so a bounded
+// series still reaches far back (by default 24 hourly then 30 daily points).
+
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{require, IntoStorageKey};
+
+use crate::time::{NANOS_PER_SECOND, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+
+// Upper bound on the points a retention may keep across all its tiers
+pub const MAX_RETAINED_POINTS: usize = 512;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RetentionTier {
+    // Bucket width in nanoseconds
+    pub interval: u64,
+    // Buckets kept at this width before points move to the next tier
+    pub points: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HistoryRetention {
+    // Finest first
+    tiers: Vec<RetentionTier>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self::new(vec![
+            RetentionTier { interval: SECONDS_PER_HOUR * NANOS_PER_SECOND, points: 24 },
+            RetentionTier { interval: SECONDS_PER_DAY * NANOS_PER_SECOND, points: 30 },
+        ])
+    }
+}
+
+impl HistoryRetention {
+    pub fn new(tiers: Vec<RetentionTier>) -> Self {
+        require!(!tiers.is_empty(), "At least one retention tier required");
+        require!(tiers.iter().all(|tier| tier.interval > 0 && tier.points > 0), "Invalid retention tier");
+        require!(tiers.windows(2).all(|w| w[0].interval < w[1].interval), "Tier intervals must increase");
+        let retention = Self { tiers };
+        require!(retention.max_points() <= MAX_RETAINED_POINTS, "Retention keeps too many points");
+        retention
+    }
+
+    pub fn tiers(&self) -> &[RetentionTier] {
+        &self.tiers
+    }
+
+    pub fn max_points(&self) -> usize {
+        self.tiers.iter().map(|tier| tier.points as usize).sum()
+    }
+
+    // Keeps the newest point in each bucket, walking back from the latest
+    // point through the tiers in turn. Points past the last tier are dropped.
+    // Expects `points` in ascending timestamp order.
+    pub fn apply<T: Copy>(&self, points: &mut Vec<(u64, T)>) {
+        let mut kept = Vec::with_capacity(points.len().min(self.max_points()));
+        let mut tiers = self.tiers.iter();
+        let mut tier = tiers.next();
+        let mut used = 0;
+        let mut last_bucket = None;
+
+        for &(timestamp, value) in points.iter().rev() {
+            while let Some(current) = tier {
+                let bucket = timestamp / current.interval;
+                if last_bucket == Some(bucket) {
+                    break;
+                }
+                if used < current.points {
+                    kept.push((timestamp, value));
+                    used += 1;
+                    last_bucket = Some(bucket);
+                    break;
+                }
+
+                // Tier full: carry on in the next, starting from the bucket
+                // the oldest kept point falls in so it isn't doubled up
+                tier = tiers.next();
+                used = 0;
+                last_bucket = tier.zip(kept.last()).map(|(next, (kept_at, _))| kept_at / next.interval);
+            }
+            if tier.is_none() {
+                break;
+            }
+        }
+
+        kept.reverse();
+        *points = kept;
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq)]
+struct Ring {
+    capacity: u32,
+    // Slot of the oldest point
+    head: u32,
+    len: u32,
+}
+
+// A retained series kept in storage as one ring per tier, so a new point only
+// writes to the tiers it spills into instead of rewriting the whole series.
+// When a tier is full its oldest point moves down to the next one; the newest
+// point in a bucket replaces the one already there.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TieredSeries {
+    slots: LookupMap<(u32, u32), (u64, Balance)>, // (tier, slot) -> point
+    rings: Vec<Ring>,
+}
+
+impl TieredSeries {
+    pub fn new<S: IntoStorageKey>(prefix: S, retention: &HistoryRetention) -> Self {
+        Self { slots: LookupMap::new(prefix), rings: Self::rings_for(retention) }
+    }
+
+    fn rings_for(retention: &HistoryRetention) -> Vec<Ring> {
+        retention.tiers().iter().map(|tier| Ring { capacity: tier.points, head: 0, len: 0 }).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rings.iter().map(|ring| ring.len as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Expects `retention` to be the one the series was laid out for, and
+    // points to arrive in timestamp order
+    pub fn push(&mut self, point: (u64, Balance), retention: &HistoryRetention) {
+        let mut carry = Some(point);
+        for (tier_index, (tier, ring)) in retention.tiers().iter().zip(self.rings.iter_mut()).enumerate() {
+            let Some(point) = carry.take() else {
+                break;
+            };
+            let tier_index = tier_index as u32;
+
+            if ring.len > 0 {
+                let newest = (tier_index, (ring.head + ring.len - 1) % ring.capacity);
+                let same_bucket = self.slots.get(&newest).is_some_and(|(timestamp, _)| {
+                    timestamp / tier.interval == point.0 / tier.interval
+                });
+                if same_bucket {
+                    self.slots.insert(&newest, &point);
+                    break;
+                }
+            }
+
+            if ring.len < ring.capacity {
+                self.slots.insert(&(tier_index, (ring.head + ring.len) % ring.capacity), &point);
+                ring.len += 1;
+            } else {
+                carry = self.slots.insert(&(tier_index, ring.head), &point);
+                ring.head = (ring.head + 1) % ring.capacity;
+            }
+        }
+    }
+
+    // Oldest first
+    pub fn to_vec(&self) -> Vec<(u64, Balance)> {
+        let mut points = Vec::with_capacity(self.len());
+        for (tier_index, ring) in self.rings.iter().enumerate().rev() {
+            for offset in 0..ring.len {
+                let slot = (tier_index as u32, (ring.head + offset) % ring.capacity);
+                points.extend(self.slots.get(&slot));
+            }
+        }
+        points
+    }
+
+    // Lays the series out again for new tiers, downsampling what is kept
+    pub fn regroup(&mut self, retention: &HistoryRetention) {
+        let points = self.to_vec();
+        self.clear();
+        self.rings = Self::rings_for(retention);
+        for point in points {
+            self.push(point, retention);
+        }
+    }
+
+    fn clear(&mut self) {
+        for (tier_index, ring) in self.rings.iter_mut().enumerate() {
+            for offset in 0..ring.len {
+                self.slots.remove(&(tier_index as u32, (ring.head + offset) % ring.capacity));
+            }
+            *ring = Ring { capacity: ring.capacity, head: 0, len: 0 };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = SECONDS_PER_HOUR * NANOS_PER_SECOND;
+    const DAY: u64 = SECONDS_PER_DAY * NANOS_PER_SECOND;
+
+    #[test]
+    fn test_tiered_retention_downsamples_old_points() {
+        let retention = HistoryRetention::default();
+        let mut series = Vec::new();
+
+        // Ten minutes apart for 60 days, retention applied on every push
+        for i in 0..(60 * 24 * 6u64) {
+            series.push((i * HOUR / 6, i));
+            retention.apply(&mut series);
+        }
+        let now = series.last().unwrap().0;
+
+        assert!(series.len() <= retention.max_points());
+        assert!(series.windows(2).all(|w| w[0].0 < w[1].0));
+
+        // The last day holds one point per hour, older ones one per day
+        let (recent, older): (Vec<&(u64, u64)>, Vec<_>) = series.iter().partition(|(t, _)| now - t < 24 * HOUR);
+        assert_eq!(recent.len(), 24);
+        assert!(recent.windows(2).all(|w| w[1].0 / HOUR == w[0].0 / HOUR + 1));
+        assert!(older.windows(2).all(|w| w[0].0 / DAY < w[1].0 / DAY));
+
+        // ...reaching back a month rather than the last 54 pushes
+        assert!(now - series[0].0 >= 29 * DAY);
+        assert_eq!(series.last().unwrap().1, 60 * 24 * 6 - 1);
+    }
+
+    #[test]
+    fn test_tiered_series_keeps_retention_layout() {
+        let retention = HistoryRetention::default();
+        let mut series = TieredSeries::new(b"t".to_vec(), &retention);
+
+        // Hourly for 40 days
+        for i in 0..(40 * 24u64) {
+            series.push((i * HOUR, i as Balance), &retention);
+        }
+        let points = series.to_vec();
+        let now = points.last().unwrap().0;
+
+        assert_eq!(points.len(), retention.max_points());
+        assert!(points.windows(2).all(|w| w[0].0 < w[1].0));
+        let (recent, older): (Vec<&(u64, Balance)>, Vec<_>) = points.iter().partition(|(t, _)| now - t < 24 * HOUR);
+        assert_eq!(recent.len(), 24);
+        assert!(recent.windows(2).all(|w| w[1].0 / HOUR == w[0].0 / HOUR + 1));
+        assert!(older.windows(2).all(|w| w[0].0 / DAY + 1 == w[1].0 / DAY));
+        assert_eq!(points.last().unwrap().1, 40 * 24 - 1);
+    }
+
+    #[test]
+    fn test_tiered_series_regrouped_for_new_tiers() {
+        let retention = HistoryRetention::default();
+        let mut series = TieredSeries::new(b"t".to_vec(), &retention);
+        for i in 0..(3 * 24u64) {
+            series.push((i * HOUR, i as Balance), &retention);
+        }
+
+        let daily = HistoryRetention::new(vec![RetentionTier { interval: DAY, points: 2 }]);
+        series.regroup(&daily);
+        assert_eq!(series.to_vec(), vec![(2 * DAY - HOUR, 47), (3 * DAY - HOUR, 71)]);
+
+        series.push((3 * DAY, 72), &daily);
+        assert_eq!(series.to_vec(), vec![(3 * DAY - HOUR, 71), (3 * DAY, 72)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Retention keeps too many points")]
+    fn test_oversized_retention_rejected() {
+        HistoryRetention::new(vec![
+            RetentionTier { interval: HOUR, points: 500 },
+            RetentionTier { interval: DAY, points: 500 },
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tier intervals must increase")]
+    fn test_unordered_tiers_rejected() {
+        HistoryRetention::new(vec![
+            RetentionTier { interval: DAY, points: 30 },
+            RetentionTier { interval: HOUR, points: 24 },
+        ]);
+    }
+}
@@ -1,9 +1,23 @@
-use near_sdk::{env, AccountId, Balance, Promise};
+use near_sdk::json_types::U128;
+use near_sdk::{env, ext_contract, require, AccountId, Gas, NearToken, Promise, PromiseError};
+use near_contract_standards::fungible_token::Balance;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use super::time::{Nanos, Seconds};
 
-const CHAINLINK_FEED_REGISTRY: &str = "feed.testnet.chainlink.near";
+pub const CHAINLINK_FEED_REGISTRY: &str = "feed.testnet.chainlink.near";
 const UPDATE_THRESHOLD: u64 = 3600; // 1 hour in seconds
 const HEARTBEAT_THRESHOLD: u64 = 86400; // 24 hours in seconds
+const MAX_PRICE_DEVIATION_BPS: u32 = 1000; // 10%
+const DEVIATION_WINDOW: u64 = 300; // 5 minutes in seconds
+const BOUNTY_CALLBACK_GAS: Gas = Gas::from_tgas(5);
+
+// Implemented by the contract holding the adapter: a #[private] method taking
+// the transfer's #[callback_result] that hands it to settle_bounty
+#[ext_contract(ext_bounty_callback)]
+pub trait BountyCallback {
+    fn on_bounty_paid(&mut self, keeper: AccountId, amount: U128);
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PriceFeed {
@@ -11,7 +25,7 @@ pub struct PriceFeed {
     pub price: u128,
     pub decimals: u8,
     pub last_update: u64,
-    pub heartbeat: u64,
+    pub heartbeat: u64, // seconds
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -22,7 +36,7 @@ pub struct APYFeed {
     pub last_update: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LiquidityMetrics {
     pub token: String,
     pub total_liquidity: Balance,
@@ -31,11 +45,72 @@ pub struct LiquidityMetrics {
     pub last_update: u64,
 }
 
+// Kinked borrow-rate curve in basis points: the rate climbs gently with
+// utilization up to `kink_bps`, then steeply above it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InterestRateModel {
+    pub base_rate_bps: u32,
+    pub slope1_bps: u32,
+    pub slope2_bps: u32,
+    pub kink_bps: u32,
+}
+
+impl Default for InterestRateModel {
+    fn default() -> Self {
+        Self {
+            base_rate_bps: 200,
+            slope1_bps: 800,
+            slope2_bps: 10_000,
+            kink_bps: 8_000,
+        }
+    }
+}
+
+impl InterestRateModel {
+    pub fn apy_at(&self, utilization_bps: u32) -> u32 {
+        let utilization = utilization_bps.min(10_000) as u64;
+        let kink = self.kink_bps.min(10_000) as u64;
+
+        if utilization <= kink {
+            let below = (self.slope1_bps as u64 * utilization).checked_div(kink).unwrap_or(0);
+            self.base_rate_bps + below as u32
+        } else {
+            let above = self.slope2_bps as u64 * (utilization - kink) / (10_000 - kink);
+            self.base_rate_bps + self.slope1_bps + above as u32
+        }
+    }
+}
+
 pub struct OracleAdapter {
     price_feeds: Vec<PriceFeed>,
     apy_feeds: Vec<APYFeed>,
     liquidity_metrics: Vec<LiquidityMetrics>,
     last_health_check: u64,
+    max_price_deviation_bps: u32,
+    deviation_window: u64, // seconds
+    alerts: Vec<String>,
+    feed_registry: HashMap<String, String>, // token -> Chainlink feed id
+    rate_model: InterestRateModel,
+    protocol_markets: HashMap<String, Vec<String>>, // protocol -> tokens it accepts
+    liquidity_markets: HashMap<String, String>, // protocol -> token its liquidity metrics are kept under
+    // Keepers refreshing a stale feed are paid up to `update_bounty` from
+    // `bounty_pool`; zero disables the bounty
+    update_bounty: Balance,
+    bounty_pool: Balance,
+    bounties_paid: HashMap<AccountId, Balance>,
+    total_bounties_paid: Balance,
+}
+
+// Feed timestamps come from the block clock (nanoseconds) while the
+// thresholds above are in seconds
+fn is_fresh(last_update: u64, max_age: Seconds) -> bool {
+    Nanos::block_now().saturating_sub(Nanos(last_update)) <= max_age.to_nanos()
+}
+
+impl Default for OracleAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OracleAdapter {
@@ -45,38 +120,165 @@ impl OracleAdapter {
             apy_feeds: Vec::new(),
             liquidity_metrics: Vec::new(),
             last_health_check: env::block_timestamp(),
+            max_price_deviation_bps: MAX_PRICE_DEVIATION_BPS,
+            deviation_window: DEVIATION_WINDOW,
+            alerts: Vec::new(),
+            feed_registry: HashMap::new(),
+            rate_model: InterestRateModel::default(),
+            protocol_markets: HashMap::new(),
+            liquidity_markets: HashMap::new(),
+            update_bounty: 0,
+            bounty_pool: 0,
+            bounties_paid: HashMap::new(),
+            total_bounties_paid: 0,
+        }
+    }
+
+    // APY feeds are per protocol; this records which tokens each one takes
+    pub fn register_market(&mut self, protocol: &str, token: &str) {
+        let tokens = self.protocol_markets.entry(protocol.to_string()).or_default();
+        if !tokens.iter().any(|t| t == token) {
+            tokens.push(token.to_string());
         }
     }
 
+    // Liquidity metrics are tracked per token, so a protocol's capacity is read
+    // from the market it lends into. Also registers that market.
+    pub fn set_liquidity_market(&mut self, protocol: &str, token: &str) {
+        self.register_market(protocol, token);
+        self.liquidity_markets.insert(protocol.to_string(), token.to_string());
+    }
+
+    // Highest live APY among protocols accepting `token`
+    pub fn best_apy_for_token(&self, token: &str) -> Option<(String, u32)> {
+        self.rank_protocols_by_apy()
+            .into_iter()
+            .find(|(protocol, _)| {
+                self.protocol_markets
+                    .get(protocol)
+                    .is_some_and(|tokens| tokens.iter().any(|t| t == token))
+            })
+    }
+
+    // APY a deposit of `deposit_amount` could expect once it joins the pool.
+    // The protocol's interest is shared with the new funds, so the rate is
+    // scaled by available / (available + deposit) from the metrics of its
+    // liquidity market; with no liquidity on record there is no capacity at all.
+    pub fn capacity_adjusted_apy(&self, protocol: &str, deposit_amount: Balance) -> u32 {
+        let Some(feed) = self.apy_feeds.iter().find(|f| f.protocol == protocol) else {
+            return 0;
+        };
+        if deposit_amount == 0 {
+            return feed.apy;
+        }
+
+        let available = self.liquidity_markets
+            .get(protocol)
+            .and_then(|token| self.liquidity_metrics.iter().find(|m| &m.token == token))
+            .map_or(0, |m| m.available_liquidity);
+        let share = available as f64 / available.saturating_add(deposit_amount) as f64;
+        (feed.apy as f64 * share) as u32
+    }
+
+    // Live APY feeds, best first
+    pub fn rank_protocols_by_apy(&self) -> Vec<(String, u32)> {
+        let mut ranked: Vec<(String, u32)> = self.apy_feeds
+            .iter()
+            .filter(|feed| is_fresh(feed.last_update, Seconds(HEARTBEAT_THRESHOLD)))
+            .map(|feed| (feed.protocol.clone(), feed.apy))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    pub fn set_rate_model(&mut self, model: InterestRateModel) {
+        self.rate_model = model;
+    }
+
+    pub fn register_feed(&mut self, token: &str, feed_id: &str) {
+        self.feed_registry.insert(token.to_string(), feed_id.to_string());
+    }
+
+    // Returns the feed id that was registered, if any
+    pub fn unregister_feed(&mut self, token: &str) -> Option<String> {
+        self.feed_registry.remove(token)
+    }
+
+    pub fn set_deviation_guard(&mut self, max_deviation_bps: u32, window: u64) {
+        self.max_price_deviation_bps = max_deviation_bps;
+        self.deviation_window = window;
+    }
+
+    pub fn set_update_bounty(&mut self, bounty: Balance) {
+        self.update_bounty = bounty;
+    }
+
+    // Called from a payable method; the attached NEAR is what funds the pool
+    pub fn fund_bounty_pool(&mut self, amount: Balance) {
+        require!(
+            env::attached_deposit().as_yoctonear() == amount,
+            "Attached deposit must match the bounty funding"
+        );
+        self.bounty_pool += amount;
+    }
+
+    pub fn bounty_pool(&self) -> Balance {
+        self.bounty_pool
+    }
+
+    pub fn total_bounties_paid(&self) -> Balance {
+        self.total_bounties_paid
+    }
+
+    pub fn bounties_paid_to(&self, keeper: &AccountId) -> Balance {
+        self.bounties_paid.get(keeper).copied().unwrap_or(0)
+    }
+
+    // Alerts raised since the last call, oldest first
+    pub fn take_alerts(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.alerts)
+    }
+
+    // A cached value older than UPDATE_THRESHOLD is re-fetched, including one
+    // past its heartbeat. That one is never handed out, so if the re-fetch
+    // fails the caller gets the error rather than a dead price.
     pub async fn fetch_price(&mut self, token: &str) -> Result<u128, String> {
         // Check cache first
+        let mut refreshing = false;
         if let Some(feed) = self.price_feeds
             .iter()
             .find(|f| f.token == token)
         {
-            if env::block_timestamp() - feed.last_update < UPDATE_THRESHOLD {
+            if is_fresh(feed.last_update, Seconds(UPDATE_THRESHOLD)) {
                 return Ok(feed.price);
             }
+            refreshing = true;
         }
 
         // Fetch from Chainlink
         let price = self.fetch_chainlink_price(token).await?;
         
-        // Update cache
-        self.update_price_feed(token, price);
+        // Update cache, refusing a sudden jump from the cached price
+        self.update_price_feed(token, price)?;
+        if refreshing {
+            self.pay_update_bounty();
+        }
         
         Ok(price)
     }
 
+    // Refreshes like fetch_price
     pub async fn fetch_apy(&mut self, protocol: &str) -> Result<u32, String> {
         // Check cache
+        let mut refreshing = false;
         if let Some(feed) = self.apy_feeds
             .iter()
             .find(|f| f.protocol == protocol)
         {
-            if env::block_timestamp() - feed.last_update < UPDATE_THRESHOLD {
+            if is_fresh(feed.last_update, Seconds(UPDATE_THRESHOLD)) {
                 return Ok(feed.apy);
             }
+            refreshing = true;
         }
 
         // Fetch from protocol
@@ -84,6 +286,9 @@ impl OracleAdapter {
         
         // Update cache
         self.update_apy_feed(protocol, apy, tvl);
+        if refreshing {
+            self.pay_update_bounty();
+        }
         
         Ok(apy)
     }
@@ -97,7 +302,7 @@ impl OracleAdapter {
             .iter()
             .find(|m| m.token == token)
         {
-            if env::block_timestamp() - metrics.last_update < UPDATE_THRESHOLD {
+            if is_fresh(metrics.last_update, Seconds(UPDATE_THRESHOLD)) {
                 return Ok(metrics.clone());
             }
         }
@@ -116,14 +321,14 @@ impl OracleAdapter {
         
         // Check price feed health
         for feed in &self.price_feeds {
-            if current_time - feed.last_update > feed.heartbeat {
+            if !is_fresh(feed.last_update, Seconds(feed.heartbeat)) {
                 return false;
             }
         }
 
         // Check APY feed health
         for feed in &self.apy_feeds {
-            if current_time - feed.last_update > HEARTBEAT_THRESHOLD {
+            if !is_fresh(feed.last_update, Seconds(HEARTBEAT_THRESHOLD)) {
                 return false;
             }
         }
@@ -132,16 +337,41 @@ impl OracleAdapter {
         true
     }
 
+    // Liveness of every cached feed, labelled "price:<token>" or "apy:<protocol>"
+    pub fn get_feed_status(&self) -> Vec<(String, bool)> {
+        let prices = self.price_feeds.iter().map(|feed| {
+            (format!("price:{}", feed.token), is_fresh(feed.last_update, Seconds(feed.heartbeat)))
+        });
+        let apys = self.apy_feeds.iter().map(|feed| {
+            (format!("apy:{}", feed.protocol), is_fresh(feed.last_update, Seconds(HEARTBEAT_THRESHOLD)))
+        });
+        prices.chain(apys).collect()
+    }
+
+    // Cached price while its feed is within heartbeat, without fetching. The
+    // vault keeper reports these for oracle-backed share valuation.
+    pub fn fresh_price(&self, token: &str) -> Option<u128> {
+        self.price_feeds
+            .iter()
+            .find(|f| f.token == token && is_fresh(f.last_update, Seconds(f.heartbeat)))
+            .map(|f| f.price)
+    }
+
     async fn fetch_chainlink_price(&self, token: &str) -> Result<u128, String> {
-        // This would call the Chainlink feed registry
+        let _feed_id = self.feed_registry
+            .get(token)
+            .ok_or_else(|| format!("No Chainlink feed registered for {}", token))?;
+
+        // This would call `_feed_id` on the CHAINLINK_FEED_REGISTRY contract
         // For now, return mock data
         Ok(1_000_000) // $1.00 with 6 decimals
     }
 
     async fn fetch_protocol_metrics(&self, protocol: &str) -> Result<(u32, Balance), String> {
-        // This would fetch actual protocol metrics
-        // For now, return mock data
-        Ok((1000, 1_000_000)) // 10% APY and $1M TVL
+        // APY follows the protocol's utilization along the rate curve
+        let liquidity = self.fetch_protocol_liquidity(protocol).await?;
+        let apy = self.rate_model.apy_at(liquidity.utilization_rate);
+        Ok((apy, liquidity.total_liquidity))
     }
 
     async fn fetch_protocol_liquidity(&self, token: &str) -> Result<LiquidityMetrics, String> {
@@ -156,11 +386,59 @@ impl OracleAdapter {
         })
     }
 
-    fn update_price_feed(&mut self, token: &str, price: u128) {
+    // Pays the caller for refreshing a stale feed, capped at update_bounty and
+    // at what is left in the pool. The bounty leaves the pool now and is
+    // recorded as paid by settle_bounty once the transfer succeeds. Returns
+    // the amount sent.
+    fn pay_update_bounty(&mut self) -> Balance {
+        let bounty = self.update_bounty.min(self.bounty_pool);
+        if bounty == 0 {
+            return 0;
+        }
+
+        let keeper = env::predecessor_account_id();
+        self.bounty_pool -= bounty;
+        Promise::new(keeper.clone()).transfer(NearToken::from_yoctonear(bounty)).then(
+            ext_bounty_callback::ext(env::current_account_id())
+                .with_static_gas(BOUNTY_CALLBACK_GAS)
+                .on_bounty_paid(keeper, U128(bounty)),
+        );
+        bounty
+    }
+
+    // Settles a bounty transfer from the host's on_bounty_paid. A failed
+    // transfer leaves the NEAR here, so it goes back to the pool. Returns
+    // whether it succeeded.
+    pub fn settle_bounty(&mut self, keeper: AccountId, amount: Balance, transfer: Result<(), PromiseError>) -> bool {
+        if transfer.is_err() {
+            self.bounty_pool += amount;
+            return false;
+        }
+        self.total_bounties_paid += amount;
+        *self.bounties_paid.entry(keeper).or_default() += amount;
+        true
+    }
+
+    fn update_price_feed(&mut self, token: &str, price: u128) -> Result<(), String> {
         if let Some(feed) = self.price_feeds
             .iter_mut()
             .find(|f| f.token == token)
         {
+            // A large move is only suspicious if the cached price is recent;
+            // after the window a gap in updates can explain it
+            if is_fresh(feed.last_update, Seconds(self.deviation_window)) && feed.price > 0 {
+                let deviation_bps = price.abs_diff(feed.price).saturating_mul(10_000) / feed.price;
+                if deviation_bps > self.max_price_deviation_bps as u128 {
+                    let alert = format!(
+                        "Rejected {} price {}: {} bps from cached {}",
+                        token, price, deviation_bps, feed.price
+                    );
+                    env::log_str(&alert);
+                    self.alerts.push(alert.clone());
+                    return Err(alert);
+                }
+            }
+
             feed.price = price;
             feed.last_update = env::block_timestamp();
         } else {
@@ -172,6 +450,7 @@ impl OracleAdapter {
                 heartbeat: HEARTBEAT_THRESHOLD,
             });
         }
+        Ok(())
     }
 
     fn update_apy_feed(&mut self, protocol: &str, apy: u32, tvl: Balance) {
@@ -207,8 +486,20 @@ impl OracleAdapter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
     use near_sdk::testing_env;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    // The mock sources never suspend, so a single poll completes them
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("oracle future suspended"),
+        }
+    }
 
     fn setup_context() {
         let context = VMContextBuilder::new()
@@ -223,18 +514,39 @@ mod tests {
         let mut oracle = OracleAdapter::new();
         
         // Add some test feeds
-        oracle.update_price_feed("ETH", 1_000_000);
+        oracle.update_price_feed("ETH", 1_000_000).unwrap();
         oracle.update_apy_feed("aave", 1000, 1_000_000);
         
         assert!(oracle.check_oracle_health());
     }
 
+    #[test]
+    fn test_health_check_uses_seconds_thresholds() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.update_price_feed("ETH", 1_000_000).unwrap();
+
+        // Two hours later the feed is still within its 24h heartbeat; comparing
+        // raw nanoseconds against the seconds threshold would flag it stale
+        let context = VMContextBuilder::new()
+            .block_timestamp(1_000_000_000 + 2 * 3600 * 1_000_000_000)
+            .build();
+        testing_env!(context);
+        assert!(oracle.check_oracle_health());
+
+        let context = VMContextBuilder::new()
+            .block_timestamp(1_000_000_000 + (HEARTBEAT_THRESHOLD + 1) * 1_000_000_000)
+            .build();
+        testing_env!(context);
+        assert!(!oracle.check_oracle_health());
+    }
+
     #[test]
     fn test_price_feed_caching() {
         setup_context();
         let mut oracle = OracleAdapter::new();
         
-        oracle.update_price_feed("BTC", 20_000_000_000);
+        oracle.update_price_feed("BTC", 20_000_000_000).unwrap();
         
         // Should use cached value
         assert_eq!(
@@ -246,4 +558,263 @@ mod tests {
             20_000_000_000
         );
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_price_update_within_deviation_accepted() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.update_price_feed("ETH", 1_000_000).unwrap();
+
+        // 5% move is inside the 10% guard
+        assert!(oracle.update_price_feed("ETH", 1_050_000).is_ok());
+        assert_eq!(oracle.price_feeds[0].price, 1_050_000);
+        assert!(oracle.take_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_price_spike_rejected_with_alert() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.update_price_feed("ETH", 1_000_000).unwrap();
+
+        assert!(oracle.update_price_feed("ETH", 3_000_000).is_err());
+        assert_eq!(oracle.price_feeds[0].price, 1_000_000);
+        let alerts = oracle.take_alerts();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("ETH"));
+
+        // Once the cached price is outside the window the move is accepted
+        let context = VMContextBuilder::new()
+            .block_timestamp(1_000_000_000 + (DEVIATION_WINDOW + 1) * 1_000_000_000)
+            .build();
+        testing_env!(context);
+        assert!(oracle.update_price_feed("ETH", 3_000_000).is_ok());
+        assert_eq!(oracle.price_feeds[0].price, 3_000_000);
+    }
+
+    #[test]
+    fn test_dead_feed_errors_unless_refetched() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.update_price_feed("ETH", 1_000_000).unwrap();
+        oracle.update_apy_feed("aave", 1000, 1_000_000);
+
+        // ETH and aave go silent for longer than the heartbeat; NEAR reports just now
+        let now = 1_000_000_000 + (HEARTBEAT_THRESHOLD + 60) * 1_000_000_000;
+        testing_env!(VMContextBuilder::new().block_timestamp(now).build());
+        oracle.update_price_feed("NEAR", 5_000_000).unwrap();
+        assert_eq!(oracle.fresh_price("ETH"), None);
+
+        // ETH has no Chainlink feed to re-fetch from, so its dead price is
+        // refused; aave's source answers and replaces the dead value
+        assert!(block_on(oracle.fetch_price("ETH")).is_err());
+        assert_eq!(block_on(oracle.fetch_apy("aave")), Ok(1_000));
+        assert_eq!(block_on(oracle.fetch_price("NEAR")), Ok(5_000_000));
+        assert_eq!(oracle.fresh_price("NEAR"), Some(5_000_000));
+
+        assert_eq!(
+            oracle.get_feed_status(),
+            vec![
+                ("price:ETH".to_string(), false),
+                ("price:NEAR".to_string(), true),
+                ("apy:aave".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_price_routed_through_registered_feed() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.register_feed("ETH", "eth-usd");
+
+        assert_eq!(block_on(oracle.fetch_price("ETH")), Ok(1_000_000));
+        assert_eq!(oracle.unregister_feed("ETH"), Some("eth-usd".to_string()));
+    }
+
+    #[test]
+    fn test_unregistered_token_rejected() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+
+        let err = block_on(oracle.fetch_price("DOGE")).unwrap_err();
+        assert!(err.contains("No Chainlink feed registered for DOGE"));
+        assert!(oracle.price_feeds.is_empty());
+    }
+
+    // ETH cached at the mock Chainlink price, aave at the mock utilization's
+    // APY, with a 10-unit bounty from a 15-unit pool
+    fn setup_bounty_oracle() -> OracleAdapter {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.register_feed("ETH", "eth-usd");
+        oracle.update_price_feed("ETH", 1_000_000).unwrap();
+        oracle.update_apy_feed("aave", 1_000, 1_000_000);
+        oracle.set_update_bounty(10);
+        testing_env!(VMContextBuilder::new()
+            .attached_deposit(NearToken::from_yoctonear(15))
+            .block_timestamp(1_000_000_000)
+            .build());
+        oracle.fund_bounty_pool(15);
+        oracle
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must match the bounty funding")]
+    fn test_unfunded_bounty_pool_rejected() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.fund_bounty_pool(15);
+    }
+
+    fn keeper_context(timestamp: u64) -> AccountId {
+        let keeper = accounts(2);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(keeper.clone())
+            .block_timestamp(timestamp)
+            .build());
+        keeper
+    }
+
+    #[test]
+    fn test_refreshing_stale_feed_pays_bounty() {
+        let mut oracle = setup_bounty_oracle();
+        let keeper = keeper_context(1_000_000_000 + (UPDATE_THRESHOLD + 1) * 1_000_000_000);
+
+        assert_eq!(block_on(oracle.fetch_price("ETH")), Ok(1_000_000));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].receiver_id, keeper);
+        assert!(matches!(
+            &receipts[0].actions[..],
+            [MockAction::Transfer { deposit, .. }] if deposit.as_yoctonear() == 10
+        ));
+
+        // Only recorded once the transfer is confirmed
+        assert_eq!(oracle.bounties_paid_to(&keeper), 0);
+        assert!(oracle.settle_bounty(keeper.clone(), 10, Ok(())));
+
+        // The second refresh is capped by what is left in the pool
+        assert_eq!(block_on(oracle.fetch_apy("aave")), Ok(1_000));
+        assert!(oracle.settle_bounty(keeper.clone(), 5, Ok(())));
+        assert_eq!(oracle.bounties_paid_to(&keeper), 15);
+        assert_eq!(oracle.total_bounties_paid(), 15);
+        assert_eq!(oracle.bounty_pool(), 0);
+    }
+
+    #[test]
+    fn test_failed_bounty_transfer_returns_to_pool() {
+        let mut oracle = setup_bounty_oracle();
+        let keeper = keeper_context(1_000_000_000 + (UPDATE_THRESHOLD + 1) * 1_000_000_000);
+
+        assert_eq!(block_on(oracle.fetch_price("ETH")), Ok(1_000_000));
+        assert_eq!(oracle.bounty_pool(), 5);
+        assert!(!oracle.settle_bounty(keeper.clone(), 10, Err(PromiseError::Failed)));
+        assert_eq!(oracle.bounty_pool(), 15);
+        assert_eq!(oracle.bounties_paid_to(&keeper), 0);
+        assert_eq!(oracle.total_bounties_paid(), 0);
+    }
+
+    #[test]
+    fn test_refreshing_dead_feed_pays_bounty() {
+        let mut oracle = setup_bounty_oracle();
+        let keeper = keeper_context(1_000_000_000 + (HEARTBEAT_THRESHOLD + 60) * 1_000_000_000);
+        assert_eq!(oracle.fresh_price("ETH"), None);
+
+        assert_eq!(block_on(oracle.fetch_price("ETH")), Ok(1_000_000));
+        assert_eq!(oracle.fresh_price("ETH"), Some(1_000_000));
+        assert!(oracle.settle_bounty(keeper.clone(), 10, Ok(())));
+        assert_eq!(oracle.bounties_paid_to(&keeper), 10);
+    }
+
+    #[test]
+    fn test_fresh_feed_pays_no_bounty() {
+        let mut oracle = setup_bounty_oracle();
+        let keeper = keeper_context(1_000_000_000 + 60 * 1_000_000_000);
+
+        assert_eq!(block_on(oracle.fetch_price("ETH")), Ok(1_000_000));
+        assert_eq!(block_on(oracle.fetch_apy("aave")), Ok(1_000));
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(oracle.bounties_paid_to(&keeper), 0);
+        assert_eq!(oracle.bounty_pool(), 15);
+    }
+
+    #[test]
+    fn test_kinked_rate_model() {
+        let model = InterestRateModel::default();
+
+        assert_eq!(model.apy_at(0), 200);
+        // Halfway to the kink: base + half of slope1
+        assert_eq!(model.apy_at(4_000), 600);
+        assert_eq!(model.apy_at(8_000), 1_000);
+        // Halfway from the kink to full: base + slope1 + half of slope2
+        assert_eq!(model.apy_at(9_000), 6_000);
+    }
+
+    #[test]
+    fn test_apy_follows_fetched_utilization() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+
+        // Mock liquidity reports 80% utilization, right at the kink
+        assert_eq!(block_on(oracle.fetch_apy("aave")), Ok(1_000));
+
+        oracle.set_rate_model(InterestRateModel { kink_bps: 5_000, ..Default::default() });
+        oracle.apy_feeds.clear();
+        assert_eq!(block_on(oracle.fetch_apy("aave")), Ok(7_000));
+    }
+
+    #[test]
+    fn test_best_apy_skips_stale_feeds() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        for protocol in ["aave", "burrow", "ref"] {
+            oracle.register_market(protocol, "USDC");
+        }
+        oracle.register_market("meta", "NEAR");
+
+        // The best USDC rate goes stale before the others report
+        oracle.update_apy_feed("aave", 2500, 1_000_000);
+        let now = 1_000_000_000 + (HEARTBEAT_THRESHOLD + 60) * 1_000_000_000;
+        testing_env!(VMContextBuilder::new().block_timestamp(now).build());
+        oracle.update_apy_feed("burrow", 900, 1_000_000);
+        oracle.update_apy_feed("ref", 1200, 1_000_000);
+        oracle.update_apy_feed("meta", 3000, 1_000_000);
+
+        assert_eq!(oracle.best_apy_for_token("USDC"), Some(("ref".to_string(), 1200)));
+        assert_eq!(oracle.best_apy_for_token("ETH"), None);
+        assert_eq!(
+            oracle.rank_protocols_by_apy(),
+            vec![
+                ("meta".to_string(), 3000),
+                ("ref".to_string(), 1200),
+                ("burrow".to_string(), 900),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capacity_adjusted_apy() {
+        setup_context();
+        let mut oracle = OracleAdapter::new();
+        oracle.update_apy_feed("aave", 1000, 1_000_000);
+        oracle.update_apy_feed("compound", 1000, 1_000_000);
+        oracle.set_liquidity_market("aave", "USDC");
+        oracle.update_liquidity_metrics(LiquidityMetrics {
+            token: "USDC".to_string(),
+            total_liquidity: 1_000_000,
+            available_liquidity: 800_000,
+            utilization_rate: 2000,
+            last_update: env::block_timestamp(),
+        });
+
+        // A deposit that is small next to the pool barely moves the rate
+        assert_eq!(oracle.capacity_adjusted_apy("aave", 8_000), 990);
+        // Matching the available liquidity halves it
+        assert_eq!(oracle.capacity_adjusted_apy("aave", 800_000), 500);
+        assert_eq!(oracle.capacity_adjusted_apy("aave", 0), 1000);
+        assert_eq!(oracle.best_apy_for_token("USDC"), Some(("aave".to_string(), 1000)));
+        // No liquidity market mapped for compound, so it has no capacity
+        assert_eq!(oracle.capacity_adjusted_apy("compound", 8_000), 0);
+    }
+}
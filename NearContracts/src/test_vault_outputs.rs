@@ -1,11 +1,12 @@
 use near_sdk::{
     AccountId,
     NearToken,
+    json_types::U128,
     test_utils::{accounts, VMContextBuilder},
     testing_env,
 };
 
-use crate::{YieldVault, VaultStatus, Strategy, Balance};
+use crate::{YieldVault, Balance};
 
 const YOCTO_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
 
@@ -38,6 +39,8 @@ pub fn demonstrate_vault_operations() {
         treasury,
         None,
         None,
+        None,
+        None,
     );
 
     println!("✅ Vault Initialized");
@@ -53,6 +56,13 @@ pub fn demonstrate_vault_operations() {
     vault.add_strategy("staking_pool".to_string(), 4000);  // 40% allocation
     vault.add_strategy("liquidity_pool".to_string(), 3000); // 30% allocation
 
+    // Liquidity pool returns thin out as the position grows
+    vault.set_strategy_yield_curve("liquidity_pool".to_string(), Some(vec![
+        (U128(0), 2500),
+        (U128(YOCTO_NEAR * 10), 1200),
+        (U128(YOCTO_NEAR * 50), 400),
+    ]));
+
     println!("Strategies Added:");
     for (name, strategy) in vault.get_all_strategies() {
         println!("- {} (Max Allocation: {}%)", name, strategy.max_allocation_bps as f32 / 100.0);
@@ -94,7 +104,14 @@ pub fn demonstrate_vault_operations() {
     vault.update_strategy_allocation("staking_pool".to_string(), 4000);
     vault.update_strategy_allocation("liquidity_pool".to_string(), 3000);
 
-    // Harvest yields
+    let summary = vault.get_strategy_summary();
+    println!("Strategy Summary:");
+    println!("- Active Strategies: {} ({} inactive)", summary.active_count, summary.inactive_count);
+    println!("- Allocated: {}% (Idle: {}%)", summary.total_allocated_bps as f32 / 100.0, summary.idle_bps as f32 / 100.0);
+    println!("- Weighted Risk Score: {}", summary.weighted_risk_score);
+
+    // Harvest yields (the demo runs in a single block, so skip the cooldown)
+    vault.set_min_harvest_interval(0);
     vault.harvest_yield();
 
     println!("Vault Metrics After Yield:");
@@ -148,6 +165,5 @@ pub fn run_demonstration() {
 
 #[test]
 fn test_vault_outputs() {
-    let treasury = AccountId::try_from("treasury.near".to_string()).unwrap();
     // ... existing code ...
 } 
\ No newline at end of file
@@ -1,14 +1,13 @@
-use super::*;
-use near_sdk::test_utils::{accounts, VMContextBuilder};
-use near_sdk::testing_env;
+use crate::analytics::*;
+use crate::ai_formatter::*;
+use crate::time::Seconds;
 use rust_decimal::Decimal;
-use rust_decimal::prelude::*;
 use std::str::FromStr;
 
 mod analytics_tests {
     use super::*;
 
-    fn setup_test_pool_metrics() -> PoolMetrics {
+    pub(super) fn setup_test_pool_metrics() -> PoolMetrics {
         PoolMetrics {
             pool_id: "test_pool".to_string(),
             pool_name: "TEST-USDC".to_string(),
@@ -90,7 +89,7 @@ mod analytics_tests {
                     peak_hours: vec![13, 14, 15],
                     historical_gas: vec![(1677649200, 145_000_000_000_000)],
                 },
-                // ... other chains
+                ..Default::default()
             },
 
             fee_structure: FeeStructure {
@@ -147,6 +146,11 @@ mod analytics_tests {
                     (1677735600, Decimal::from_str("-0.015").unwrap()),
                     (1677822000, Decimal::from_str("-0.01").unwrap()),
                 ],
+                share_price_history: vec![
+                    (1677649200, Decimal::from_str("1.00").unwrap()),
+                    (1677735600, Decimal::from_str("1.01").unwrap()),
+                    (1677822000, Decimal::from_str("1.018").unwrap()),
+                ],
             },
         }
     }
@@ -171,6 +175,215 @@ mod analytics_tests {
         assert!(enhanced_metrics.optimization_metrics.optimal_position_size > Decimal::ZERO);
         assert!(!enhanced_metrics.optimization_metrics.entry_signals.is_empty());
     }
+
+    #[test]
+    fn test_alpha_against_benchmarks() {
+        let base_metrics = setup_test_pool_metrics();
+        let calm_market = BenchmarkConfig {
+            market_return: Decimal::from_str("0.005").unwrap(),
+            risk_free_rate: Decimal::from_str("0.001").unwrap(),
+            ..BenchmarkConfig::default()
+        };
+        let hot_market = BenchmarkConfig {
+            market_return: Decimal::from_str("0.02").unwrap(),
+            risk_free_rate: Decimal::from_str("0.001").unwrap(),
+            ..BenchmarkConfig::default()
+        };
+
+        let vs_calm = EnhancedPoolMetrics::new_with_benchmark(base_metrics.clone(), &calm_market);
+        let vs_hot = EnhancedPoolMetrics::new_with_benchmark(base_metrics, &hot_market);
+
+        // Same return series, so alpha moves one-for-one with the benchmark
+        let alpha_gap = vs_calm.advanced_metrics.alpha_score - vs_hot.advanced_metrics.alpha_score;
+        assert_eq!(alpha_gap, hot_market.market_return - calm_market.market_return);
+        assert!(vs_calm.advanced_metrics.alpha_score > vs_hot.advanced_metrics.alpha_score);
+
+        // Risk-free rate feeds the Sharpe ratio
+        assert_eq!(vs_calm.advanced_metrics.sharpe_ratio, vs_hot.advanced_metrics.sharpe_ratio);
+    }
+
+    #[test]
+    fn test_beta_against_market_series() {
+        let market: Vec<Decimal> = ["0.01", "-0.02", "0.015", "0.005", "-0.01"]
+            .iter()
+            .map(|r| Decimal::from_str(r).unwrap())
+            .collect();
+        let leveraged: Vec<Decimal> = market.iter().map(|r| *r * Decimal::TWO).collect();
+
+        let beta = EnhancedPoolMetrics::calculate_beta(&leveraged, &market);
+        assert_decimal_range(beta, Decimal::from_str("1.999").unwrap(), Decimal::from_str("2.001").unwrap());
+
+        // Flat market has no variance to measure against
+        let flat = vec![Decimal::new(1, 2); market.len()];
+        assert_eq!(EnhancedPoolMetrics::calculate_beta(&leveraged, &flat), Decimal::ONE);
+    }
+
+    fn decimals(values: &[&str]) -> Vec<Decimal> {
+        values.iter().map(|v| Decimal::from_str(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_rsi_textbook_series() {
+        // Wilder's 14-period example series as published by StockCharts. Their sheet
+        // rounds the intermediate averages and shows 70.53 / 37.77; unrounded
+        // smoothing gives the values below.
+        let closes = decimals(&[
+            "44.34", "44.09", "44.15", "43.61", "44.33", "44.83", "45.10", "45.42",
+            "45.84", "46.08", "45.89", "46.03", "45.61", "46.28", "46.28", "46.00",
+            "46.03", "46.41", "46.22", "45.64", "46.21", "46.25", "45.71", "46.45",
+            "45.78", "45.35", "44.03", "44.18", "44.22", "44.57", "43.42", "42.66",
+            "43.13",
+        ]);
+
+        let first = calculate_rsi(&closes[..15], 14).unwrap();
+        assert_decimal_range(first, Decimal::from_str("70.45").unwrap(), Decimal::from_str("70.47").unwrap());
+
+        let last = calculate_rsi(&closes, 14).unwrap();
+        assert_decimal_range(last, Decimal::from_str("37.78").unwrap(), Decimal::from_str("37.80").unwrap());
+
+        // Not enough changes for a 14-period average
+        assert!(calculate_rsi(&closes[..14], 14).is_none());
+    }
+
+    #[test]
+    fn test_macd_on_linear_trend() {
+        // On a steady +1/period trend a seeded EMA lags by (n - 1) / 2, so
+        // MACD = 12.5 - 5.5 = 7 and the signal line settles at 7 as well
+        let closes: Vec<Decimal> = (0..60).map(|i| Decimal::from(100 + i)).collect();
+
+        let (macd, signal) = calculate_macd(&closes).unwrap();
+        let tolerance = Decimal::new(1, 9);
+        assert!((macd - Decimal::from(7)).abs() < tolerance);
+        assert!((signal - Decimal::from(7)).abs() < tolerance);
+
+        // Signal line needs 26 + 9 - 1 closes
+        assert!(calculate_macd(&closes[..33]).is_none());
+        assert!(calculate_macd(&closes[..34]).is_some());
+    }
+
+    #[test]
+    fn test_balance_return_large_values() {
+        let yocto = 10u128.pow(24);
+        // 1M NEAR falling to 900k NEAR, beyond Decimal's range as raw yocto
+        let decline = balance_return(1_000_000 * yocto, 900_000 * yocto).unwrap();
+        assert_eq!(decline, Decimal::from_str("-0.1").unwrap());
+
+        // A sub-NEAR change on a huge balance still comes out negative
+        let tiny = balance_return(u128::MAX / 2, u128::MAX / 2 - 10u128.pow(12)).unwrap();
+        assert!(tiny < Decimal::ZERO);
+
+        assert_eq!(balance_return(0, yocto), None);
+    }
+
+    #[test]
+    fn test_resample_gappy_series() {
+        let series = vec![(0, 1.0), (10, 2.0), (40, 5.0)];
+
+        let filled = resample(&series, 10, ResampleMode::ForwardFill);
+        assert_eq!(filled, vec![(0, 1.0), (10, 2.0), (20, 2.0), (30, 2.0), (40, 5.0)]);
+
+        let linear = resample(&series, 10, ResampleMode::Linear);
+        assert_eq!(linear, vec![(0, 1.0), (10, 2.0), (20, 3.0), (30, 4.0), (40, 5.0)]);
+
+        // Grid points between observations that don't line up with the input
+        let offset = resample(&[(0, 0.0), (15, 3.0)], 10, ResampleMode::Linear);
+        assert_eq!(offset, vec![(0, 0.0), (10, 2.0)]);
+
+        // Unsorted input is handled
+        let shuffled = vec![(40, 5.0), (0, 1.0), (10, 2.0)];
+        assert_eq!(resample(&shuffled, 10, ResampleMode::ForwardFill), filled);
+    }
+
+    #[test]
+    fn test_percent_b_breakout() {
+        let mut prices = Vec::new();
+        for i in 0..20 {
+            prices.push(if i % 2 == 0 { Decimal::from_str("10.0").unwrap() } else { Decimal::from_str("10.2").unwrap() });
+        }
+        let k = Decimal::TWO;
+
+        // Ranging market stays inside the bands
+        let bands = calculate_bollinger_bands(&prices, 20, k).unwrap();
+        let inside = calculate_percent_b(*prices.last().unwrap(), &bands);
+        assert!(inside >= Decimal::ZERO && inside <= Decimal::ONE);
+
+        // A breakout pushes %B above 1 even with the spike included in the window
+        let breakout = Decimal::from_str("11.0").unwrap();
+        prices.push(breakout);
+        let bands = calculate_bollinger_bands(&prices, 20, k).unwrap();
+        assert!(calculate_percent_b(breakout, &bands) > Decimal::ONE);
+
+        // Flat prices collapse the bands; %B is neutral instead of dividing by zero
+        let flat = vec![Decimal::from(10); 20];
+        let bands = calculate_bollinger_bands(&flat, 20, k).unwrap();
+        assert_eq!(calculate_percent_b(Decimal::from(10), &bands), Decimal::new(5, 1));
+
+        assert!(calculate_bollinger_bands(&flat[..19], 20, k).is_none());
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let base_metrics = setup_test_pool_metrics();
+        let enhanced_metrics = EnhancedPoolMetrics::new(base_metrics);
+        let earlier = enhanced_metrics.snapshot(1677649200);
+        assert_eq!(earlier.apy, Decimal::from_str("0.15").unwrap());
+        assert_eq!(earlier.risk_score, 35);
+
+        let later = MetricsSnapshot {
+            timestamp: 1677735600,
+            apy: Decimal::from_str("0.12").unwrap(),
+            risk_score: 50,
+            sharpe_ratio: earlier.sharpe_ratio + Decimal::from_str("0.5").unwrap(),
+        };
+
+        let delta = earlier.diff(&later);
+        assert_eq!(delta.elapsed, 86400);
+        assert_eq!(delta.apy_change, Decimal::from_str("-0.03").unwrap());
+        assert_eq!(delta.risk_score_change, 15);
+        assert_eq!(delta.sharpe_change, Decimal::from_str("0.5").unwrap());
+
+        // Diffing the other way round flips every sign
+        let reverse = later.diff(&earlier);
+        assert_eq!(reverse.elapsed, -86400);
+        assert_eq!(reverse.risk_score_change, -15);
+        assert_eq!(reverse.apy_change, Decimal::from_str("0.03").unwrap());
+    }
+
+    #[test]
+    fn test_max_drawdown_over_share_price() {
+        let prices: Vec<(u64, Decimal)> = ["1.00", "1.20", "1.10", "0.90", "1.30", "1.17"]
+            .iter()
+            .enumerate()
+            .map(|(day, price)| (day as u64 * 86400, Decimal::from_str(price).unwrap()))
+            .collect();
+
+        // Peak 1.20 -> trough 0.90 is -25%; the later 1.30 -> 1.17 dip is only -10%
+        let drawdown = EnhancedPoolMetrics::calculate_max_drawdown(&prices);
+        assert_eq!(drawdown, Decimal::from_str("-0.25").unwrap());
+
+        // Monotonic growth never draws down
+        assert_eq!(EnhancedPoolMetrics::calculate_max_drawdown(&prices[..2]), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_sortino_matches_hand_computed_value() {
+        let returns: Vec<Decimal> = ["0.02", "-0.01", "0.03", "-0.02"]
+            .iter()
+            .map(|r| Decimal::from_str(r).unwrap())
+            .collect();
+
+        // Shortfalls below MAR 0: -0.01, -0.02 -> (0.0001 + 0.0004) / 4 periods = 0.000125
+        let downside = EnhancedPoolMetrics::calculate_downside_deviation(&returns, Decimal::ZERO);
+        assert_decimal_range(downside, Decimal::from_str("0.011180").unwrap(), Decimal::from_str("0.011181").unwrap());
+
+        // Mean 0.005 / 0.0111803 = 0.4472136
+        let sortino = EnhancedPoolMetrics::calculate_sortino_ratio(&returns, Decimal::ZERO);
+        assert_decimal_range(sortino, Decimal::from_str("0.447213").unwrap(), Decimal::from_str("0.447214").unwrap());
+
+        // A higher MAR counts more shortfall and lowers the ratio
+        let stricter = EnhancedPoolMetrics::calculate_sortino_ratio(&returns, Decimal::from_str("0.01").unwrap());
+        assert!(stricter < sortino);
+    }
 }
 
 mod ai_formatter_tests {
@@ -201,6 +414,122 @@ mod ai_formatter_tests {
         assert!(ai_input.optimization_features.optimal_position_size >= 0.0);
         assert!(ai_input.optimization_features.timing_efficiency >= 0.0 && ai_input.optimization_features.timing_efficiency <= 1.0);
     }
+
+    #[test]
+    fn test_seasonality_with_nanosecond_timestamps() {
+        const NANOS: u64 = 1_000_000_000;
+        // Day 3 of the week, hours 5 and 6, recorded as vault nanoseconds
+        let start = 1_700_000_000 / 604800 * 604800 + 3 * 86400 + 5 * 3600;
+        let mut metrics = analytics_tests::setup_test_pool_metrics();
+        metrics.performance_history.volume_history = vec![
+            (start * NANOS, 100),
+            ((start + 3600) * NANOS, 300),
+        ];
+
+        let seasonality = TemporalFeatures::from(&metrics).seasonality;
+        assert_eq!(seasonality.len(), 31);
+
+        // Same buckets as the equivalent second-resolution history
+        let mut seconds_metrics = metrics.clone();
+        seconds_metrics.performance_history.volume_history = vec![(start, 100), (start + 3600, 300)];
+        assert_eq!(TemporalFeatures::from(&seconds_metrics).seasonality, seasonality);
+
+        assert!((seasonality[5] - 100.0 / 400.0).abs() < 1e-9);
+        assert!((seasonality[6] - 300.0 / 400.0).abs() < 1e-9);
+        assert!((seasonality[24 + 3] - 1.0).abs() < 1e-9);
+        let others: f64 = seasonality.iter().enumerate()
+            .filter(|(i, _)| ![5, 6, 27].contains(i))
+            .map(|(_, v)| v)
+            .sum();
+        assert_eq!(others, 0.0);
+    }
+
+    #[test]
+    fn test_declining_large_volume_trend() {
+        let yocto = 10u128.pow(24);
+        let mut metrics = analytics_tests::setup_test_pool_metrics();
+        metrics.performance_history.volume_history = vec![
+            (0, 5_000_000 * yocto),
+            (86400, 4_500_000 * yocto),
+            (172800, 4_000_000 * yocto),
+        ];
+        metrics.performance_history.tvl_history = metrics.performance_history.volume_history.clone();
+
+        let market = MarketFeatures::from(&metrics);
+        assert!((market.volume_trend - (-0.2)).abs() < 1e-12);
+        assert!((market.tvl_trend - (-0.2)).abs() < 1e-12);
+
+        let trends = TemporalFeatures::from(&metrics).trend_indicators;
+        assert!((trends[0] - (-0.1)).abs() < 1e-12); // TVL falls 10% of the start per day
+    }
+
+    #[test]
+    fn test_pool_age_units() {
+        const NANOS: u64 = 1_000_000_000;
+        let created = 1_700_000_000;
+        let now = Seconds(created + 10 * 86400 + 3600);
+
+        assert_eq!(pool_age_days(created, now), 10);
+        // A vault-recorded nanosecond creation time gives the same age
+        assert_eq!(pool_age_days(created * NANOS, now), 10);
+        // Creation in the future is a new pool rather than an underflow
+        assert_eq!(pool_age_days(created + 86400, Seconds(created)), 0);
+    }
+
+    #[test]
+    fn test_feature_row_has_stable_columns() {
+        let base_metrics = analytics_tests::setup_test_pool_metrics();
+        let mut other_metrics = base_metrics.clone();
+        other_metrics.platform = "OtherDex".to_string();
+        other_metrics.pool_type = PoolType::Stable;
+        other_metrics.performance_history.daily_returns.truncate(1);
+
+        let row = EnhancedAIModelInput::from(&EnhancedPoolMetrics::new(base_metrics)).to_feature_row();
+        let other_row = EnhancedAIModelInput::from(&EnhancedPoolMetrics::new(other_metrics)).to_feature_row();
+
+        let names: Vec<&String> = row.iter().map(|(name, _)| name).collect();
+        let other_names: Vec<&String> = other_row.iter().map(|(name, _)| name).collect();
+        assert!(!names.is_empty());
+        assert_eq!(names, other_names);
+
+        // One-hot encodings expand into indexed columns
+        assert!(names.iter().any(|name| name.as_str() == "pool_features.pool_type_encoding[0]"));
+        assert!(names.iter().any(|name| name.as_str() == "pool_features.tvl_normalized"));
+
+        // Unique per process and call so parallel or repeated runs never share a file
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir()
+            .join(format!("citadel_feature_rows_{}_{}.csv", std::process::id(), nanos));
+        write_csv(&[row.clone(), other_row], &path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap().split(',').count(), row.len());
+        assert_eq!(lines.count(), 2);
+    }
+
+    #[test]
+    fn test_feature_confidence_flags_thin_history() {
+        let mut sparse_metrics = analytics_tests::setup_test_pool_metrics();
+        sparse_metrics.performance_history.daily_returns.truncate(1);
+        sparse_metrics.performance_history.volume_history.clear();
+
+        let enhanced_metrics = EnhancedPoolMetrics::new(sparse_metrics);
+        let ai_input = EnhancedAIModelInput::from(&enhanced_metrics);
+        let confidence = &ai_input.feature_confidence;
+
+        // Features built on one or zero points are flagged
+        assert_eq!(confidence["volume_trend"], 0.0);
+        assert_eq!(confidence["sharpe_ratio"], 0.0);
+        assert_eq!(confidence["trend_strength"], 0.0);
+
+        // Untouched history is still trusted
+        assert_eq!(confidence["tvl_trend"], 1.0);
+        assert_eq!(confidence["max_drawdown"], 1.0);
+    }
 }
 
 mod optimization_tests {
@@ -271,15 +600,15 @@ mod integration_tests {
         assert!((total_tvl_share - 1.0).abs() < 0.01); // Should sum to approximately 1
 
         // Verify gas efficiency
-        for (_, efficiency) in &cross_chain.bridge_efficiency {
-            assert!(*efficiency >= 0.0 && *efficiency <= 1.0);
+        for efficiency in cross_chain.bridge_efficiency.values() {
+            assert_f64_range(*efficiency, 0.0, 1.0);
         }
 
         // Verify chain correlations
         assert!(!cross_chain.chain_correlation.is_empty());
         for row in &cross_chain.chain_correlation {
             for &corr in row {
-                assert!(corr >= -1.0 && corr <= 1.0);
+                assert_f64_range(corr, -1.0, 1.0);
             }
         }
     }
@@ -0,0 +1,109 @@
+// Explicit time units. The NEAR runtime reports nanoseconds while analytics
+// and oracle data use Unix seconds, so raw u64 timestamps are wrapped at the
+// boundaries where the two meet.
+
+pub const NANOS_PER_SECOND: u64 = 1_000_000_000;
+pub const SECONDS_PER_HOUR: u64 = 3_600;
+pub const SECONDS_PER_DAY: u64 = 86_400;
+pub const SECONDS_PER_WEEK: u64 = 7 * SECONDS_PER_DAY;
+pub const SECONDS_PER_YEAR: u64 = 365 * SECONDS_PER_DAY;
+pub const NANOS_PER_YEAR: u64 = SECONDS_PER_YEAR * NANOS_PER_SECOND;
+
+// Raw timestamps at or above this are nanoseconds; read as seconds they
+// would be tens of millions of years out
+const NANOSECOND_TIMESTAMP_THRESHOLD: u64 = 1_000_000_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Seconds(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Nanos(pub u64);
+
+impl Seconds {
+    // Interpret a timestamp of unknown resolution, as found in mixed
+    // analytics histories
+    pub fn from_timestamp(timestamp: u64) -> Self {
+        if timestamp >= NANOSECOND_TIMESTAMP_THRESHOLD {
+            Nanos(timestamp).to_seconds()
+        } else {
+            Seconds(timestamp)
+        }
+    }
+
+    pub fn to_nanos(self) -> Nanos {
+        Nanos(self.0.saturating_mul(NANOS_PER_SECOND))
+    }
+
+    pub fn saturating_sub(self, other: Seconds) -> Seconds {
+        Seconds(self.0.saturating_sub(other.0))
+    }
+
+    pub fn whole_days(self) -> u64 {
+        self.0 / SECONDS_PER_DAY
+    }
+}
+
+impl Nanos {
+    pub fn block_now() -> Self {
+        Nanos(near_sdk::env::block_timestamp())
+    }
+
+    // Truncates any sub-second remainder
+    pub fn to_seconds(self) -> Seconds {
+        Seconds(self.0 / NANOS_PER_SECOND)
+    }
+
+    pub fn saturating_sub(self, other: Nanos) -> Nanos {
+        Nanos(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<Seconds> for Nanos {
+    fn from(seconds: Seconds) -> Self {
+        seconds.to_nanos()
+    }
+}
+
+impl From<Nanos> for Seconds {
+    fn from(nanos: Nanos) -> Self {
+        nanos.to_seconds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_conversions() {
+        assert_eq!(Seconds(90).to_nanos(), Nanos(90_000_000_000));
+        assert_eq!(Nanos(1_999_999_999).to_seconds(), Seconds(1));
+        assert_eq!(Nanos::from(Seconds(SECONDS_PER_YEAR)), Nanos(NANOS_PER_YEAR));
+        assert_eq!(Seconds::from(Nanos(NANOS_PER_YEAR)).whole_days(), 365);
+
+        // Overflow saturates rather than wrapping
+        assert_eq!(Seconds(u64::MAX).to_nanos(), Nanos(u64::MAX));
+    }
+
+    #[test]
+    fn test_from_timestamp_detects_resolution() {
+        let seconds = 1_700_000_000;
+        assert_eq!(Seconds::from_timestamp(seconds), Seconds(seconds));
+        assert_eq!(Seconds::from_timestamp(seconds * NANOS_PER_SECOND), Seconds(seconds));
+    }
+
+    #[test]
+    fn test_age_does_not_underflow() {
+        let created = Seconds(1_700_000_000);
+        let now = created.saturating_sub(Seconds(SECONDS_PER_DAY));
+
+        // A creation time in the future is an age of zero, not a wrapped u64
+        assert_eq!(now.saturating_sub(created), Seconds(0));
+        assert_eq!(Seconds(created.0 + 3 * SECONDS_PER_DAY).saturating_sub(created).whole_days(), 3);
+
+        // Mixing a nanosecond clock with a seconds creation time is caught by
+        // converting at the boundary
+        let block_now = Seconds(created.0 + 2 * SECONDS_PER_DAY).to_nanos();
+        assert_eq!(block_now.to_seconds().saturating_sub(created).whole_days(), 2);
+    }
+}
@@ -0,0 +1,91 @@
+// Size-dependent strategy yield for simulations. Each point sets the APY
+// earned on the slice of balance between its threshold and the next, so a
+// falling curve gives decreasing marginal yield as a strategy grows.
+
+use near_contract_standards::fungible_token::Balance;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::require;
+
+use crate::time::{Nanos, NANOS_PER_YEAR};
+use crate::{mul_div, BASIS_POINTS};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CurveYieldAdapter {
+    // (balance threshold, APY bps), thresholds ascending from 0
+    points: Vec<(Balance, u32)>,
+}
+
+impl CurveYieldAdapter {
+    pub fn new(points: Vec<(Balance, u32)>) -> Self {
+        require!(points.first().is_some_and(|(threshold, _)| *threshold == 0), "Curve must start at zero balance");
+        require!(points.windows(2).all(|w| w[0].0 < w[1].0), "Curve thresholds must increase");
+        Self { points }
+    }
+
+    // APY earned by the next unit deposited at `balance`
+    pub fn marginal_apy_bps(&self, balance: Balance) -> u32 {
+        self.points
+            .iter()
+            .rev()
+            .find(|(threshold, _)| balance >= *threshold)
+            .map_or(0, |(_, apy)| *apy)
+    }
+
+    // Blended APY across the whole balance
+    pub fn effective_apy_bps(&self, balance: Balance) -> u32 {
+        if balance == 0 {
+            return self.marginal_apy_bps(0);
+        }
+        let annual_yield = self.yield_for(balance, Nanos(NANOS_PER_YEAR));
+        mul_div(annual_yield, BASIS_POINTS as u128, balance) as u32
+    }
+
+    // Yield on `balance` held for `elapsed`, each slice at its own rate
+    pub fn yield_for(&self, balance: Balance, elapsed: Nanos) -> Balance {
+        let mut total = 0;
+        for (i, (threshold, apy)) in self.points.iter().enumerate() {
+            if balance <= *threshold {
+                break;
+            }
+            let upper = self.points.get(i + 1).map_or(balance, |(next, _)| balance.min(*next));
+            total += mul_div(
+                upper - threshold,
+                *apy as u128 * elapsed.0 as u128,
+                BASIS_POINTS as u128 * NANOS_PER_YEAR as u128,
+            );
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+    fn falling_curve() -> CurveYieldAdapter {
+        CurveYieldAdapter::new(vec![(0, 2000), (10 * NEAR, 1000), (20 * NEAR, 500)])
+    }
+
+    #[test]
+    fn test_marginal_yield_decreases() {
+        let curve = falling_curve();
+        assert_eq!(curve.marginal_apy_bps(0), 2000);
+        assert_eq!(curve.marginal_apy_bps(15 * NEAR), 1000);
+        assert_eq!(curve.marginal_apy_bps(100 * NEAR), 500);
+
+        // 10 at 20%, 10 at 10%, 10 at 5% for a year
+        assert_eq!(curve.yield_for(30 * NEAR, Nanos(NANOS_PER_YEAR)), 35 * NEAR / 10);
+        assert_eq!(curve.effective_apy_bps(10 * NEAR), 2000);
+        assert_eq!(curve.effective_apy_bps(30 * NEAR), 1166);
+    }
+
+    #[test]
+    #[should_panic(expected = "Curve thresholds must increase")]
+    fn test_unordered_curve_rejected() {
+        CurveYieldAdapter::new(vec![(0, 2000), (10 * NEAR, 1000), (10 * NEAR, 500)]);
+    }
+}
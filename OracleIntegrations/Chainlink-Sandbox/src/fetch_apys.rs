@@ -1,27 +1,41 @@
-use crate::{Asset, Protocol, ApyData, OracleError, OracleAdapter};
-use async_trait::async_trait;
+use crate::{Asset, Protocol, ApyData, LiquidityData, OracleError, OracleAdapter, MaxAge};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Fewest fresh readings `aggregate_apy` accepts by default
+const DEFAULT_MIN_RESPONSES: usize = 3;
+
 /// APY fetcher for different protocols
 pub struct ApyFetcher {
     oracle: Box<dyn OracleAdapter>,
-    max_age: u64,  // Maximum age of data in seconds
+    max_age: MaxAge,
+    min_responses: usize,
 }
 
 impl ApyFetcher {
+    /// Same maximum age (seconds) for every data type
     pub fn new(oracle: Box<dyn OracleAdapter>, max_age: u64) -> Self {
-        Self { oracle, max_age }
+        Self::with_max_age(oracle, MaxAge::uniform(max_age))
+    }
+
+    pub fn with_max_age(oracle: Box<dyn OracleAdapter>, max_age: MaxAge) -> Self {
+        Self { oracle, max_age, min_responses: DEFAULT_MIN_RESPONSES }
     }
 
-    /// Validate data freshness
-    fn validate_timestamp(&self, timestamp: u64) -> Result<(), OracleError> {
+    /// Fewest fresh readings `aggregate_apy` will combine
+    pub fn with_min_responses(mut self, min_responses: usize) -> Self {
+        self.min_responses = min_responses.max(1);
+        self
+    }
+
+    /// Validate data freshness against `max_age` seconds
+    fn validate_timestamp(&self, timestamp: u64, max_age: u64) -> Result<(), OracleError> {
         let current = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
 
-        if current - timestamp > self.max_age {
+        if current.saturating_sub(timestamp) > max_age {
             return Err(OracleError::StaleData {
                 current,
                 received: timestamp,
@@ -42,7 +56,7 @@ impl ApyFetcher {
         for protocol in protocols {
             let apys = self.oracle.get_apys(assets, protocol).await?;
             for (asset, apy_data) in apys {
-                self.validate_timestamp(apy_data.timestamp)?;
+                self.validate_timestamp(apy_data.timestamp, self.max_age.apy)?;
                 results.insert((asset, protocol.clone()), apy_data);
             }
         }
@@ -60,7 +74,7 @@ impl ApyFetcher {
 
         for protocol in protocols {
             if let Ok(apy_data) = self.oracle.get_apy(asset, protocol).await {
-                self.validate_timestamp(apy_data.timestamp)?;
+                self.validate_timestamp(apy_data.timestamp, self.max_age.apy)?;
 
                 match &best {
                     None => best = Some((protocol.clone(), apy_data)),
@@ -75,6 +89,62 @@ impl ApyFetcher {
         Ok(best)
     }
 
+    /// Combine APY readings for one asset and protocol from several sources.
+    /// Stale readings are dropped; the result carries the median APY, the mean
+    /// risk score and the oldest timestamp used.
+    pub fn aggregate_apy(
+        &self,
+        asset: &Asset,
+        protocol: &Protocol,
+        sources: &[ApyData],
+    ) -> Result<ApyData, OracleError> {
+        let mut fresh: Vec<&ApyData> = sources
+            .iter()
+            .filter(|data| self.validate_timestamp(data.timestamp, self.max_age.apy).is_ok())
+            .filter(|data| data.apy.is_finite())
+            .collect();
+
+        if fresh.len() < self.min_responses {
+            return Err(OracleError::InsufficientSources {
+                got: fresh.len(),
+                required: self.min_responses,
+            });
+        }
+        if fresh.is_empty() {
+            return Err(OracleError::FetchError(format!("No APY readings for {:?}", asset)));
+        }
+
+        fresh.sort_by(|a, b| a.apy.total_cmp(&b.apy));
+        let mid = fresh.len() / 2;
+        let apy = if fresh.len().is_multiple_of(2) {
+            (fresh[mid - 1].apy + fresh[mid].apy) / 2.0
+        } else {
+            fresh[mid].apy
+        };
+
+        let risk_total: u32 = fresh.iter().map(|data| data.risk_score as u32).sum();
+        let risk_score = (risk_total as f64 / fresh.len() as f64).round() as u8;
+        let timestamp = fresh.iter().map(|data| data.timestamp).min().unwrap_or_default();
+
+        Ok(ApyData {
+            apy,
+            timestamp,
+            protocol: protocol.clone(),
+            risk_score,
+        })
+    }
+
+    /// Get liquidity for an asset in a protocol, checked against the liquidity max age
+    pub async fn get_liquidity(
+        &self,
+        asset: &Asset,
+        protocol: &Protocol,
+    ) -> Result<LiquidityData, OracleError> {
+        let liquidity = self.oracle.get_liquidity(asset, protocol).await?;
+        self.validate_timestamp(liquidity.timestamp, self.max_age.liquidity)?;
+        Ok(liquidity)
+    }
+
     /// Get APY history for an asset in a specific protocol
     pub async fn get_apy_history(
         &self,
@@ -122,13 +192,15 @@ impl ApyFetcher {
 mod tests {
     use super::*;
     use crate::mock::MockOracle;
+    use crate::{PriceData, PriceFetcher};
+    use near_sdk::json_types::U128;
     use tokio_test::block_on;
 
     #[test]
     fn test_apy_fetcher() {
         block_on(async {
             let mock_oracle = MockOracle::new();
-            let fetcher = ApyFetcher::new(Box::new(mock_oracle), 3600);
+            let fetcher = ApyFetcher::new(Box::new(mock_oracle.clone()), 3600);
 
             let eth = Asset::Token("ETH".to_string());
             let aave = Protocol::Aave;
@@ -167,7 +239,7 @@ mod tests {
 
             // Test multi-protocol fetch
             let multi = fetcher.get_multi_protocol_apys(
-                &[eth.clone()],
+                std::slice::from_ref(&eth),
                 &[aave.clone(), compound.clone()]
             ).await.unwrap();
             assert_eq!(multi.len(), 2);
@@ -175,4 +247,82 @@ mod tests {
             assert!(multi.contains_key(&(eth.clone(), compound.clone())));
         });
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_max_age_per_data_type() {
+        block_on(async {
+            let mock_oracle = MockOracle::new();
+            let max_age = MaxAge { price: 60, apy: 3600, liquidity: 600 };
+            let price_fetcher = PriceFetcher::with_max_age(Box::new(mock_oracle.clone()), max_age);
+            let apy_fetcher = ApyFetcher::with_max_age(Box::new(mock_oracle.clone()), max_age);
+
+            let eth = Asset::Token("ETH".to_string());
+            let two_minutes_ago = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() - 120;
+
+            mock_oracle.set_price(eth.clone(), PriceData {
+                price: U128(1_500_000_000_000),
+                timestamp: two_minutes_ago,
+                source: "mock".to_string(),
+            }).await;
+            mock_oracle.set_apy(eth.clone(), Protocol::Aave, ApyData {
+                apy: 0.05,
+                timestamp: two_minutes_ago,
+                protocol: Protocol::Aave,
+                risk_score: 2,
+            }).await;
+
+            // Too old for a price, fine for an APY
+            assert!(matches!(
+                price_fetcher.get_prices(std::slice::from_ref(&eth)).await,
+                Err(OracleError::StaleData { .. })
+            ));
+            let best = apy_fetcher.find_best_apy(&eth, &[Protocol::Aave]).await.unwrap();
+            assert_eq!(best.unwrap().1.apy, 0.05);
+        });
+    }
+
+    fn reading(apy: f64, risk_score: u8, age: u64) -> ApyData {
+        ApyData {
+            apy,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() - age,
+            protocol: Protocol::Aave,
+            risk_score,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_apy_median_ignores_outlier() {
+        let fetcher = ApyFetcher::new(Box::new(MockOracle::new()), 3600);
+        let eth = Asset::Token("ETH".to_string());
+
+        let sources = [reading(0.05, 2, 10), reading(0.90, 5, 0), reading(0.052, 2, 20)];
+        let aggregated = fetcher.aggregate_apy(&eth, &Protocol::Aave, &sources).unwrap();
+
+        assert_eq!(aggregated.apy, 0.052);
+        assert_eq!(aggregated.risk_score, 3);
+        assert_eq!(aggregated.timestamp, sources[2].timestamp);
+    }
+
+    #[test]
+    fn test_aggregate_apy_requires_min_responses() {
+        let fetcher = ApyFetcher::new(Box::new(MockOracle::new()), 3600);
+        let eth = Asset::Token("ETH".to_string());
+
+        // The stale reading doesn't count towards the minimum
+        let sources = [reading(0.05, 2, 10), reading(0.06, 2, 10), reading(0.055, 2, 7200)];
+        assert!(matches!(
+            fetcher.aggregate_apy(&eth, &Protocol::Aave, &sources),
+            Err(OracleError::InsufficientSources { got: 2, required: 3 })
+        ));
+
+        let fetcher = fetcher.with_min_responses(2);
+        let aggregated = fetcher.aggregate_apy(&eth, &Protocol::Aave, &sources).unwrap();
+        assert!((aggregated.apy - 0.055).abs() < 1e-12);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Asset, PriceData, OracleError, OracleAdapter};
+use crate::{Asset, PriceData, OracleError, OracleAdapter, MaxAge};
 use near_sdk::json_types::U128;
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -6,22 +6,27 @@ use std::time::{SystemTime, UNIX_EPOCH};
 /// Price fetcher for tokens and pools
 pub struct PriceFetcher {
     oracle: Box<dyn OracleAdapter>,
-    max_age: u64,  // Maximum age of data in seconds
+    max_age: MaxAge,
 }
 
 impl PriceFetcher {
+    /// Same maximum age (seconds) for every data type
     pub fn new(oracle: Box<dyn OracleAdapter>, max_age: u64) -> Self {
+        Self::with_max_age(oracle, MaxAge::uniform(max_age))
+    }
+
+    pub fn with_max_age(oracle: Box<dyn OracleAdapter>, max_age: MaxAge) -> Self {
         Self { oracle, max_age }
     }
 
-    /// Validate data freshness
-    fn validate_timestamp(&self, timestamp: u64) -> Result<(), OracleError> {
+    /// Validate data freshness against `max_age` seconds
+    fn validate_timestamp(&self, timestamp: u64, max_age: u64) -> Result<(), OracleError> {
         let current = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
 
-        if current - timestamp > self.max_age {
+        if current.saturating_sub(timestamp) > max_age {
             return Err(OracleError::StaleData {
                 current,
                 received: timestamp,
@@ -37,7 +42,7 @@ impl PriceFetcher {
         
         // Validate all timestamps
         for price_data in prices.values() {
-            self.validate_timestamp(price_data.timestamp)?;
+            self.validate_timestamp(price_data.timestamp, self.max_age.price)?;
         }
 
         Ok(prices)
@@ -50,7 +55,7 @@ impl PriceFetcher {
         period: u64,  // Period in seconds
     ) -> Result<f64, OracleError> {
         let current_price = self.oracle.get_price(asset).await?;
-        self.validate_timestamp(current_price.timestamp)?;
+        self.validate_timestamp(current_price.timestamp, self.max_age.price)?;
 
         // Note: In a real implementation, we would fetch historical price
         // For now, we'll simulate a 1% change
@@ -72,16 +77,15 @@ impl PriceFetcher {
     pub async fn calculate_volatility(
         &self,
         asset: &Asset,
-        period: u64,
+        _period: u64,
         samples: u32,
     ) -> Result<u8, OracleError> {
         // Note: In a real implementation, we would fetch historical prices
         // For now, we'll simulate some price changes
         let mut changes = Vec::new();
         let current_price = self.oracle.get_price(asset).await?;
-        self.validate_timestamp(current_price.timestamp)?;
+        self.validate_timestamp(current_price.timestamp, self.max_age.price)?;
 
-        let base_price = current_price.price.0;
         for i in 0..samples {
             // Simulate price changes with some randomness
             let change = (i as f64 * 0.01) - 0.005;  // -0.5% to +0.5%
@@ -118,7 +122,7 @@ impl PriceFetcher {
         amount: U128,
     ) -> Result<f64, OracleError> {
         let current_price = self.oracle.get_price(asset).await?;
-        self.validate_timestamp(current_price.timestamp)?;
+        self.validate_timestamp(current_price.timestamp, self.max_age.price)?;
 
         // Note: This is a simplified model. In reality, we would:
         // 1. Fetch pool liquidity data
@@ -141,7 +145,7 @@ mod tests {
     fn test_price_fetcher() {
         block_on(async {
             let mock_oracle = MockOracle::new();
-            let fetcher = PriceFetcher::new(Box::new(mock_oracle), 3600);
+            let fetcher = PriceFetcher::new(Box::new(mock_oracle.clone()), 3600);
 
             let eth = Asset::Token("ETH".to_string());
             let usdc = Asset::Token("USDC".to_string());
@@ -180,7 +184,7 @@ mod tests {
 
             // Test volatility calculation
             let volatility = fetcher.calculate_volatility(&eth, 86400, 10).await.unwrap();
-            assert!(volatility >= 0 && volatility <= 100);
+            assert!(volatility <= 100);
         });
     }
 } 
\ No newline at end of file
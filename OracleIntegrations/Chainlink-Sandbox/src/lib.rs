@@ -2,7 +2,18 @@ use async_trait::async_trait;
 use near_sdk::json_types::U128;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+pub mod fetch_apys;
+pub mod fetch_prices;
+
+pub use fetch_apys::ApyFetcher;
+pub use fetch_prices::PriceFetcher;
 
 /// Represents different types of assets we track
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -47,6 +58,30 @@ pub struct LiquidityData {
     pub timestamp: u64,
 }
 
+/// Price, APY and liquidity for one asset in one protocol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    pub price: PriceData,
+    pub apy: ApyData,
+    pub liquidity: LiquidityData,
+}
+
+/// Maximum accepted data age in seconds, per data type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxAge {
+    pub price: u64,
+    pub apy: u64,
+    pub liquidity: u64,
+}
+
+impl MaxAge {
+    /// Same threshold for every data type
+    pub fn uniform(max_age: u64) -> Self {
+        Self { price: max_age, apy: max_age, liquidity: max_age }
+    }
+
+}
+
 /// Oracle errors
 #[derive(Error, Debug)]
 pub enum OracleError {
@@ -64,11 +99,14 @@ pub enum OracleError {
     
     #[error("Data too old: current={current}, received={received}")]
     StaleData { current: u64, received: u64 },
+
+    #[error("Not enough sources: got {got}, need {required}")]
+    InsufficientSources { got: usize, required: usize },
 }
 
 /// Main oracle adapter trait
 #[async_trait]
-pub trait OracleAdapter {
+pub trait OracleAdapter: Send + Sync {
     /// Fetch current price for an asset
     async fn get_price(&self, asset: &Asset) -> Result<PriceData, OracleError>;
     
@@ -83,6 +121,16 @@ pub trait OracleAdapter {
     
     /// Fetch multiple APYs at once
     async fn get_apys(&self, assets: &[Asset], protocol: &Protocol) -> Result<HashMap<Asset, ApyData>, OracleError>;
+
+    /// Fetch price, APY and liquidity together. Fails if any part is missing;
+    /// backends with a batched endpoint can override this.
+    async fn get_snapshot(&self, asset: &Asset, protocol: &Protocol) -> Result<MarketSnapshot, OracleError> {
+        Ok(MarketSnapshot {
+            price: self.get_price(asset).await?,
+            apy: self.get_apy(asset, protocol).await?,
+            liquidity: self.get_liquidity(asset, protocol).await?,
+        })
+    }
 }
 
 /// Chainlink oracle implementation
@@ -198,6 +246,155 @@ impl OracleAdapter for ChainlinkOracle {
     }
 }
 
+type OracleFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, OracleError>> + Send + 'a>>;
+
+/// Tries the primary oracle, then each fallback in order until one answers.
+/// Price sources are prefixed with the oracle that answered ("primary" or
+/// "fallback[i]"); if every oracle fails the primary's error is returned.
+pub struct FallbackOracle {
+    pub primary: Box<dyn OracleAdapter>,
+    pub fallbacks: Vec<Box<dyn OracleAdapter>>,
+}
+
+impl FallbackOracle {
+    pub fn new(primary: Box<dyn OracleAdapter>, fallbacks: Vec<Box<dyn OracleAdapter>>) -> Self {
+        Self { primary, fallbacks }
+    }
+
+    async fn first_success<'a, T>(
+        &'a self,
+        fetch: impl Fn(&'a dyn OracleAdapter) -> OracleFuture<'a, T>,
+    ) -> Result<(String, T), OracleError> {
+        let primary_error = match fetch(self.primary.as_ref()).await {
+            Ok(data) => return Ok(("primary".to_string(), data)),
+            Err(e) => e,
+        };
+
+        for (i, fallback) in self.fallbacks.iter().enumerate() {
+            if let Ok(data) = fetch(fallback.as_ref()).await {
+                return Ok((format!("fallback[{}]", i), data));
+            }
+        }
+
+        Err(primary_error)
+    }
+
+    fn tag_source(label: &str, mut price: PriceData) -> PriceData {
+        price.source = format!("{}:{}", label, price.source);
+        price
+    }
+}
+
+#[async_trait]
+impl OracleAdapter for FallbackOracle {
+    async fn get_price(&self, asset: &Asset) -> Result<PriceData, OracleError> {
+        let (label, price) = self.first_success(|oracle| oracle.get_price(asset)).await?;
+        Ok(Self::tag_source(&label, price))
+    }
+
+    async fn get_apy(&self, asset: &Asset, protocol: &Protocol) -> Result<ApyData, OracleError> {
+        let (_, apy) = self.first_success(|oracle| oracle.get_apy(asset, protocol)).await?;
+        Ok(apy)
+    }
+
+    async fn get_liquidity(&self, asset: &Asset, protocol: &Protocol) -> Result<LiquidityData, OracleError> {
+        let (_, liquidity) = self.first_success(|oracle| oracle.get_liquidity(asset, protocol)).await?;
+        Ok(liquidity)
+    }
+
+    async fn get_prices(&self, assets: &[Asset]) -> Result<HashMap<Asset, PriceData>, OracleError> {
+        let (label, prices) = self.first_success(|oracle| oracle.get_prices(assets)).await?;
+        Ok(prices
+            .into_iter()
+            .map(|(asset, price)| (asset, Self::tag_source(&label, price)))
+            .collect())
+    }
+
+    async fn get_apys(&self, assets: &[Asset], protocol: &Protocol) -> Result<HashMap<Asset, ApyData>, OracleError> {
+        let (_, apys) = self.first_success(|oracle| oracle.get_apys(assets, protocol)).await?;
+        Ok(apys)
+    }
+}
+
+/// Token-bucket limit around another oracle. Each request takes a token;
+/// when the bucket is empty the request waits for a refill instead of failing.
+pub struct RateLimitedOracle {
+    inner: Box<dyn OracleAdapter>,
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<TokenBucket>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimitedOracle {
+    /// `capacity` is the allowed burst size, `refill_per_second` the sustained rate
+    pub fn new(inner: Box<dyn OracleAdapter>, capacity: u32, refill_per_second: f64) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        assert!(refill_per_second > 0.0, "refill rate must be positive");
+
+        Self {
+            inner,
+            capacity: capacity as f64,
+            refill_per_second,
+            bucket: Mutex::new(TokenBucket {
+                tokens: capacity as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let refilled = now.duration_since(bucket.last_refill).as_secs_f64() * self.refill_per_second;
+                bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[async_trait]
+impl OracleAdapter for RateLimitedOracle {
+    async fn get_price(&self, asset: &Asset) -> Result<PriceData, OracleError> {
+        self.acquire().await;
+        self.inner.get_price(asset).await
+    }
+
+    async fn get_apy(&self, asset: &Asset, protocol: &Protocol) -> Result<ApyData, OracleError> {
+        self.acquire().await;
+        self.inner.get_apy(asset, protocol).await
+    }
+
+    async fn get_liquidity(&self, asset: &Asset, protocol: &Protocol) -> Result<LiquidityData, OracleError> {
+        self.acquire().await;
+        self.inner.get_liquidity(asset, protocol).await
+    }
+
+    async fn get_prices(&self, assets: &[Asset]) -> Result<HashMap<Asset, PriceData>, OracleError> {
+        self.acquire().await;
+        self.inner.get_prices(assets).await
+    }
+
+    async fn get_apys(&self, assets: &[Asset], protocol: &Protocol) -> Result<HashMap<Asset, ApyData>, OracleError> {
+        self.acquire().await;
+        self.inner.get_apys(assets, protocol).await
+    }
+}
+
 /// Mock oracle for testing
 #[cfg(test)]
 pub mod mock {
@@ -205,20 +402,35 @@ pub mod mock {
     use std::sync::Arc;
     use tokio::sync::RwLock;
     
+    /// Clones share the same underlying data
+    #[derive(Clone)]
     pub struct MockOracle {
         prices: Arc<RwLock<HashMap<Asset, PriceData>>>,
         apys: Arc<RwLock<HashMap<(Asset, Protocol), ApyData>>>,
         liquidity: Arc<RwLock<HashMap<(Asset, Protocol), LiquidityData>>>,
+        calls: Arc<RwLock<Vec<Instant>>>,
     }
     
+    impl Default for MockOracle {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl MockOracle {
         pub fn new() -> Self {
             Self {
                 prices: Arc::new(RwLock::new(HashMap::new())),
                 apys: Arc::new(RwLock::new(HashMap::new())),
                 liquidity: Arc::new(RwLock::new(HashMap::new())),
+                calls: Arc::new(RwLock::new(Vec::new())),
             }
         }
+
+        /// When each price request arrived
+        pub fn calls(&self) -> Arc<RwLock<Vec<Instant>>> {
+            self.calls.clone()
+        }
         
         pub async fn set_price(&self, asset: Asset, price: PriceData) {
             self.prices.write().await.insert(asset, price);
@@ -236,6 +448,7 @@ pub mod mock {
     #[async_trait]
     impl OracleAdapter for MockOracle {
         async fn get_price(&self, asset: &Asset) -> Result<PriceData, OracleError> {
+            self.calls.write().await.push(Instant::now());
             self.prices
                 .read()
                 .await
@@ -339,4 +552,141 @@ mod tests {
             ));
         });
     }
-} 
\ No newline at end of file
+
+    /// Oracle that fails every request with its name
+    struct FailingOracle(&'static str);
+
+    #[async_trait]
+    impl OracleAdapter for FailingOracle {
+        async fn get_price(&self, _asset: &Asset) -> Result<PriceData, OracleError> {
+            Err(OracleError::FetchError(self.0.to_string()))
+        }
+
+        async fn get_apy(&self, _asset: &Asset, _protocol: &Protocol) -> Result<ApyData, OracleError> {
+            Err(OracleError::FetchError(self.0.to_string()))
+        }
+
+        async fn get_liquidity(&self, _asset: &Asset, _protocol: &Protocol) -> Result<LiquidityData, OracleError> {
+            Err(OracleError::FetchError(self.0.to_string()))
+        }
+
+        async fn get_prices(&self, _assets: &[Asset]) -> Result<HashMap<Asset, PriceData>, OracleError> {
+            Err(OracleError::FetchError(self.0.to_string()))
+        }
+
+        async fn get_apys(&self, _assets: &[Asset], _protocol: &Protocol) -> Result<HashMap<Asset, ApyData>, OracleError> {
+            Err(OracleError::FetchError(self.0.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_fallback_supplies_data_when_primary_fails() {
+        block_on(async {
+            let eth = Asset::Token("ETH".to_string());
+            let backup = mock_with_price_and_apy(&eth, &Protocol::Aave).await;
+            let oracle = FallbackOracle::new(
+                Box::new(FailingOracle("primary down")),
+                vec![Box::new(FailingOracle("backup down")), Box::new(backup)],
+            );
+
+            let price = oracle.get_price(&eth).await.unwrap();
+            assert_eq!(price.price, U128(1_500_000_000_000));
+            assert_eq!(price.source, "fallback[1]:mock");
+
+            let prices = oracle.get_prices(std::slice::from_ref(&eth)).await.unwrap();
+            assert_eq!(prices[&eth].source, "fallback[1]:mock");
+            assert_eq!(oracle.get_apy(&eth, &Protocol::Aave).await.unwrap().apy, 0.05);
+        });
+    }
+
+    #[test]
+    fn test_fallback_returns_primary_error_when_all_fail() {
+        block_on(async {
+            let eth = Asset::Token("ETH".to_string());
+            let oracle = FallbackOracle::new(
+                Box::new(FailingOracle("primary down")),
+                vec![Box::new(FailingOracle("backup down"))],
+            );
+
+            match oracle.get_price(&eth).await {
+                Err(OracleError::FetchError(message)) => assert_eq!(message, "primary down"),
+                other => panic!("expected the primary's error, got {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn test_rate_limited_burst_is_throttled() {
+        block_on(async {
+            let eth = Asset::Token("ETH".to_string());
+            let mock = mock_with_price_and_apy(&eth, &Protocol::Aave).await;
+            let calls = mock.calls();
+            // Burst of 2, then 20 requests per second
+            let oracle = RateLimitedOracle::new(Box::new(mock), 2, 20.0);
+
+            for _ in 0..6 {
+                oracle.get_price(&eth).await.unwrap();
+            }
+
+            let calls = calls.read().await;
+            assert_eq!(calls.len(), 6);
+            // The burst goes straight through; the other four wait 50ms each
+            assert!(calls[1].duration_since(calls[0]) < Duration::from_millis(20));
+            let throttled = calls[5].duration_since(calls[1]);
+            assert!(throttled >= Duration::from_millis(190), "elapsed {:?}", throttled);
+            assert!(throttled < Duration::from_millis(600), "elapsed {:?}", throttled);
+        });
+    }
+
+    async fn mock_with_price_and_apy(asset: &Asset, protocol: &Protocol) -> mock::MockOracle {
+        let oracle = mock::MockOracle::new();
+        oracle.set_price(asset.clone(), PriceData {
+            price: U128(1_500_000_000_000),
+            timestamp: 1234567890,
+            source: "mock".to_string(),
+        }).await;
+        oracle.set_apy(asset.clone(), protocol.clone(), ApyData {
+            apy: 0.05,
+            timestamp: 1234567890,
+            protocol: protocol.clone(),
+            risk_score: 2,
+        }).await;
+        oracle
+    }
+
+    #[test]
+    fn test_snapshot_combines_all_data() {
+        block_on(async {
+            let eth = Asset::Token("ETH".to_string());
+            let oracle = mock_with_price_and_apy(&eth, &Protocol::Aave).await;
+            oracle.set_liquidity(eth.clone(), Protocol::Aave, LiquidityData {
+                total_liquidity: U128(1_000_000),
+                available_liquidity: U128(800_000),
+                utilization_rate: 0.2,
+                timestamp: 1234567890,
+            }).await;
+
+            let snapshot = oracle.get_snapshot(&eth, &Protocol::Aave).await.unwrap();
+            assert_eq!(snapshot.price.price, U128(1_500_000_000_000));
+            assert_eq!(snapshot.apy.apy, 0.05);
+            assert_eq!(snapshot.liquidity.available_liquidity, U128(800_000));
+        });
+    }
+
+    #[test]
+    fn test_snapshot_with_missing_liquidity_fails() {
+        block_on(async {
+            let eth = Asset::Token("ETH".to_string());
+            let oracle = mock_with_price_and_apy(&eth, &Protocol::Aave).await;
+
+            assert!(matches!(
+                oracle.get_snapshot(&eth, &Protocol::Aave).await,
+                Err(OracleError::UnsupportedAsset(_))
+            ));
+        });
+    }
+}
+
+#[cfg(test)]
+#[path = "tests.rs"]
+mod fetcher_tests;
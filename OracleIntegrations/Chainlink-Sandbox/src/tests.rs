@@ -41,7 +41,7 @@ fn test_price_fetching_functionality() {
         mock_oracle.set_price(btc.clone(), btc_price.clone()).await;
 
         // Test single price fetch
-        let fetched_eth = fetcher.get_prices(&[eth.clone()]).await.unwrap();
+        let fetched_eth = fetcher.get_prices(std::slice::from_ref(&eth)).await.unwrap();
         assert_eq!(fetched_eth.get(&eth).unwrap().price, eth_price.price);
 
         // Test multiple price fetch
@@ -56,14 +56,14 @@ fn test_price_fetching_functionality() {
 
         // Test volatility calculation
         let volatility = fetcher.calculate_volatility(&eth, 86400, 10).await.unwrap();
-        assert!(volatility >= 0 && volatility <= 100);
+        assert!(volatility <= 100);
 
         // Test price impact
         let impact = fetcher.estimate_price_impact(&eth, U128(1_000_000_000_000)).await.unwrap();
         assert!(impact > 0.0 && impact <= 100.0);
 
         // Test error handling for unsupported asset
-        let result = fetcher.get_prices(&[usdc.clone()]).await;
+        let result = fetcher.get_prices(std::slice::from_ref(&usdc)).await;
         assert!(matches!(result, Err(OracleError::UnsupportedAsset(_))));
     });
 }
@@ -98,7 +98,7 @@ fn test_apy_fetching_functionality() {
 
         // Test multi-protocol APY fetch
         let apys = fetcher.get_multi_protocol_apys(
-            &[eth.clone()],
+            std::slice::from_ref(&eth),
             &[aave.clone(), compound.clone()]
         ).await.unwrap();
         assert_eq!(apys.len(), 2);
@@ -120,7 +120,7 @@ fn test_apy_fetching_functionality() {
 
         // Test APY volatility
         let volatility = fetcher.calculate_apy_volatility(&eth, &aave, 86400).await.unwrap();
-        assert!(volatility >= 0 && volatility <= 100);
+        assert!(volatility <= 100);
     });
 }
 
@@ -140,7 +140,7 @@ fn test_stale_data_handling() {
         mock_oracle.set_price(eth.clone(), stale_price).await;
 
         // Test stale data rejection
-        let result = fetcher.get_prices(&[eth.clone()]).await;
+        let result = fetcher.get_prices(std::slice::from_ref(&eth)).await;
         assert!(matches!(result, Err(OracleError::StaleData { .. })));
     });
 }
@@ -177,9 +177,6 @@ fn test_chainlink_oracle_requests() {
 
     // Note: These tests would need a mock HTTP server in a real implementation
     // For now, we just verify the request formation logic
-    let eth = Asset::Token("ETH".to_string());
-    let aave = Protocol::Aave;
-
     assert_eq!(
         format!("{}/{}", oracle.endpoint, "price/ETH"),
         "https://api.chain.link/v1/price/ETH"
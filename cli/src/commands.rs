@@ -1,3 +1,8 @@
+pub mod bridge;
+pub mod monitoring;
+pub mod oracle;
+pub mod vault;
+
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use colored::*;
@@ -7,8 +12,9 @@ use parking_lot::RwLock;
 use prettytable::{Cell, Row, Table};
 use reqwest;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time;
@@ -214,8 +220,9 @@ fn create_progress_bar(message: &str) -> ProgressBar {
     let pb = ProgressBar::new_spinner();
     pb.set_style(ProgressStyle::default_spinner()
         .template("{spinner:.green} {msg}")
+        .expect("Failed to set progress style")
         .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ "));
-    pb.set_message(message);
+    pb.set_message(message.to_string());
     pb
 }
 
@@ -234,15 +241,112 @@ fn format_number(num: u64) -> String {
 
 // Main functions
 async fn fetch_protocol_tvl(protocol: &str) -> Result<f64, TvlError> {
-    // Implementation remains the same
+    let client = reqwest::Client::new();
+    let cache_key = format!("tvl_{}", protocol);
+
+    // Try to get from cache first
+    if let Ok(cached_value) = METRICS_CACHE.read().get(&cache_key) {
+        if let Some(tvl) = cached_value.as_f64() {
+            return Ok(tvl);
+        }
+    }
+
+    // Fetch from API if not in cache
+    let url = match protocol {
+        "aave" => "https://api.aave.com/v1/tvl",
+        "compound" => "https://api.compound.finance/v2/tvl",
+        "uniswap" => "https://api.uniswap.org/v1/tvl",
+        _ => return Err(TvlError::ProtocolError(format!("Unsupported protocol: {}", protocol)))
+    };
+
+    let response = client
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(TvlError::NetworkError)?;
+
+    if response.status() == 429 {
+        return Err(TvlError::RateLimitError);
+    }
+
+    let data: Value = response
+        .json()
+        .await
+        .map_err(TvlError::NetworkError)?;
+
+    let tvl = data["tvl"]
+        .as_f64()
+        .ok_or(TvlError::InvalidResponseFormat)?;
+
+    // Cache the result
+    let _ = METRICS_CACHE.write().set(cache_key, json!(tvl));
+
+    Ok(tvl)
 }
 
 async fn fetch_total_tvl() -> Result<f64> {
-    // Implementation remains the same
+    let protocols = ["aave", "compound", "uniswap"];
+
+    let tvl_futures = protocols.iter().map(|&protocol| {
+        let protocol = protocol.to_string();
+        async move {
+            with_retry(TvlFetcher { protocol: protocol.clone() }, 3)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to fetch TVL for {} after retries: {}", protocol, e);
+                    e
+                })
+        }
+    });
+
+    let tvls: Vec<f64> = join_all(tvl_futures).await
+        .into_iter()
+        .filter_map(|result| result.ok())
+        .collect();
+
+    if tvls.is_empty() {
+        return Err(anyhow!("Failed to fetch TVL from any protocol"));
+    }
+
+    Ok(tvls.iter().sum())
 }
 
 async fn fetch_protocol_apy(protocol: &str) -> Result<ProtocolApy, ApyError> {
-    // Implementation remains the same
+    let client = reqwest::Client::new();
+
+    let url = match protocol {
+        "aave" => "https://api.aave.com/v1/apy",
+        "compound" => "https://api.compound.finance/v2/apy",
+        "uniswap" => "https://api.uniswap.org/v1/apy",
+        _ => return Err(ApyError::ProtocolError(format!("Unsupported protocol: {}", protocol)))
+    };
+
+    let response = client
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(ApyError::NetworkError)?;
+
+    if response.status() == 429 {
+        return Err(ApyError::RateLimitError);
+    }
+
+    let data: Value = response
+        .json()
+        .await
+        .map_err(ApyError::NetworkError)?;
+
+    let field = |name: &str| data[name].as_f64().ok_or(ApyError::InvalidResponseFormat);
+
+    Ok(ProtocolApy {
+        lending_apy: field("lending_apy")?,
+        borrowing_apy: field("borrowing_apy")?,
+        liquidity_apy: field("liquidity_apy")?,
+        total_apy: field("total_apy")?,
+        weight: data["weight"].as_f64().unwrap_or(1.0),
+    })
 }
 
 async fn fetch_current_apy() -> Result<f64> {
@@ -358,11 +462,9 @@ async fn fetch_users_for_protocol(protocol: &str) -> Result<Vec<String>, UserErr
         .collect::<Vec<String>>();
 
     // Cache the result
-    if let Ok(mut cache) = METRICS_CACHE.write() {
-        let cache_value = serde_json::to_value(&users)
-            .map_err(|_| UserError::InvalidResponseFormat)?;
-        cache.set(cache_key, cache_value)?;
-    }
+    let cache_value = serde_json::to_value(&users)
+        .map_err(|_| UserError::InvalidResponseFormat)?;
+    let _ = METRICS_CACHE.write().set(cache_key, cache_value);
 
     Ok(users)
 }
@@ -388,12 +490,12 @@ async fn fetch_active_users() -> Result<u64> {
 
             match fetch_result {
                 Ok(users) => {
-                    gauge!("protocol.users.count", users.len() as f64, "protocol" => &protocol);
+                    gauge!("protocol.users.count", "protocol" => protocol.clone()).set(users.len() as f64);
                     log::info!("Fetched {} users from {}", users.len(), protocol);
                     Ok((protocol, users))
                 }
                 Err(e) => {
-                    counter!("protocol.users.errors", 1, "protocol" => protocol.clone());
+                    counter!("protocol.users.errors", "protocol" => protocol.clone()).increment(1);
                     log::error!("Failed to fetch users from {} after retries: {}", protocol, e);
                     Err(e)
                 }
@@ -418,9 +520,9 @@ async fn fetch_active_users() -> Result<u64> {
 
     // Record metrics
     let success_rate = success_count as f64 / protocols.len() as f64;
-    gauge!("users.fetch.success_rate", success_rate);
-    gauge!("users.fetch.duration_ms", start_time.elapsed().as_millis() as f64);
-    gauge!("users.total_unique", unique_users.len() as f64);
+    gauge!("users.fetch.success_rate").set(success_rate);
+    gauge!("users.fetch.duration_ms").set(start_time.elapsed().as_millis() as f64);
+    gauge!("users.total_unique").set(unique_users.len() as f64);
 
     if unique_users.is_empty() {
         let error_msg = format!(
@@ -560,9 +662,7 @@ pub async fn get_info() -> Result<()> {
         });
 
         // Update cache
-        if let Ok(mut cache) = METRICS_CACHE.write() {
-            let _ = cache.set("vault_metrics".to_string(), metrics.clone());
-        }
+        let _ = METRICS_CACHE.write().set("vault_metrics".to_string(), metrics.clone());
 
         metrics
     };
@@ -673,9 +773,11 @@ pub enum CommandError {
     TimeoutError(String),
     #[error("Internal error: {0}")]
     InternalError(String),
+    #[error("Protocol error: {0}")]
+    ProtocolError(#[from] ProtocolError),
 }
 
-type CommandResult<T> = Result<T, CommandError>;
+pub type CommandResult<T> = Result<T, CommandError>;
 
 #[derive(Debug, Clone)]
 struct MetricsTracker {
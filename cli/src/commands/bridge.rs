@@ -19,7 +19,7 @@ pub async fn bridge_tokens(
         "Bridging {} tokens from {} to {}...",
         amount.0, from_chain, to_chain
     ));
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate bridging operation
     tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
@@ -40,7 +40,7 @@ pub async fn get_bridge_status(tx_hash: &str) -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message(format!("Checking bridge status for tx: {}...", tx_hash));
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate status check
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -64,7 +64,7 @@ pub async fn list_supported_chains() -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message("Fetching supported chains...");
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate fetching chains
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -11,7 +11,7 @@ pub async fn get_system_health() -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message("Checking system health...");
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate health check
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -37,7 +37,7 @@ pub async fn get_transaction_metrics() -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message("Fetching transaction metrics...");
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate fetching metrics
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -63,7 +63,7 @@ pub async fn get_alerts() -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message("Checking system alerts...");
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate alert check
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -12,7 +12,7 @@ pub async fn get_price(token_symbol: &str) -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message(format!("Fetching price for {}...", token_symbol));
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate price fetch
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -27,6 +27,30 @@ pub async fn get_price(token_symbol: &str) -> CommandResult<()> {
     Ok(())
 }
 
+pub async fn get_tvl(protocol: &str) -> CommandResult<()> {
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .expect("Failed to set progress style")
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
+    );
+    pb.set_message(format!("Fetching TVL for {}...", protocol));
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    // Simulate TVL fetch
+    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+
+    pb.finish_and_clear();
+    println!("\nTVL Information for {}:", protocol);
+    println!("Total Value Locked: $123,456,789");
+    println!("24h Change: +2.34%");
+    println!("Last Updated: 30 seconds ago");
+    println!("Data Source: Chainlink");
+
+    Ok(())
+}
+
 pub async fn list_supported_pairs() -> CommandResult<()> {
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -36,7 +60,7 @@ pub async fn list_supported_pairs() -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message("Fetching supported trading pairs...");
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate fetching pairs
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -63,7 +87,7 @@ pub async fn get_historical_prices(token_symbol: &str, days: u32) -> CommandResu
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message(format!("Fetching {}-day price history for {}...", days, token_symbol));
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate fetching historical data
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -1,6 +1,12 @@
 use crate::commands::{CommandResult, CommandError};
 use near_sdk::json_types::U128;
 use indicatif::{ProgressBar, ProgressStyle};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const YOCTO_NEAR: f64 = 1_000_000_000_000_000_000_000_000.0;
 
 pub async fn deposit(amount: U128) -> CommandResult<()> {
     let pb = ProgressBar::new_spinner();
@@ -11,7 +17,7 @@ pub async fn deposit(amount: U128) -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message(format!("Depositing {} tokens...", amount.0));
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate deposit operation
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -29,7 +35,7 @@ pub async fn withdraw(amount: U128) -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message(format!("Withdrawing {} tokens...", amount.0));
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate withdrawal operation
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -47,7 +53,7 @@ pub async fn get_info() -> CommandResult<()> {
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
     );
     pb.set_message("Fetching vault information...");
-    pb.enable_steady_tick(100);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Simulate fetching information
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -60,4 +66,374 @@ pub async fn get_info() -> CommandResult<()> {
     println!("Active Users: 150");
 
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// Read-only access to a deployed vault's view methods. Returns the raw JSON
+/// the contract produced so large balances are decoded without going
+/// through floating point.
+#[async_trait]
+pub trait VaultView: Send + Sync {
+    async fn view(&self, method: &str, args: Value) -> CommandResult<Vec<u8>>;
+}
+
+/// `VaultView` over NEAR JSON-RPC `call_function` queries
+pub struct RpcVaultView {
+    rpc_url: String,
+    contract_id: String,
+    http: reqwest::Client,
+}
+
+impl RpcVaultView {
+    /// `network` is "mainnet", "testnet" or a full RPC URL
+    pub fn new(network: &str, contract_id: &str) -> Self {
+        let rpc_url = match network {
+            "mainnet" => "https://rpc.mainnet.near.org".to_string(),
+            "testnet" => "https://rpc.testnet.near.org".to_string(),
+            url => url.to_string(),
+        };
+        Self {
+            rpc_url,
+            contract_id: contract_id.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl VaultView for RpcVaultView {
+    async fn view(&self, method: &str, args: Value) -> CommandResult<Vec<u8>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "vault-cli",
+            "method": "query",
+            "params": {
+                "request_type": "call_function",
+                "finality": "final",
+                "account_id": self.contract_id,
+                "method_name": method,
+                "args_base64": base64_encode(args.to_string().as_bytes()),
+            }
+        });
+
+        let response: Value = self.http
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| CommandError::ApiError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| CommandError::ApiError(e.to_string()))?;
+
+        decode_view_response(method, &response)
+    }
+}
+
+/// Pull the contract's return bytes out of a `call_function` RPC response
+pub fn decode_view_response(method: &str, response: &Value) -> CommandResult<Vec<u8>> {
+    if let Some(error) = response.get("error") {
+        return Err(CommandError::ApiError(format!("{} failed: {}", method, error)));
+    }
+    if let Some(error) = response.pointer("/result/error") {
+        return Err(CommandError::ApiError(format!("{} failed: {}", method, error)));
+    }
+
+    response
+        .pointer("/result/result")
+        .and_then(Value::as_array)
+        .ok_or_else(|| CommandError::ApiError(format!("{} returned no result", method)))?
+        .iter()
+        .map(|byte| {
+            byte.as_u64()
+                .and_then(|b| u8::try_from(b).ok())
+                .ok_or_else(|| CommandError::ApiError(format!("{} returned malformed bytes", method)))
+        })
+        .collect()
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+async fn view_as<T: DeserializeOwned>(view: &dyn VaultView, method: &str, args: Value) -> CommandResult<T> {
+    let bytes = view.view(method, args).await?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| CommandError::ApiError(format!("Unexpected {} response: {}", method, e)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoMetrics {
+    pub total_value_locked: u128,
+    pub annual_percentage_yield: u32,
+    pub total_users: u32,
+    pub total_profit: u128,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoStrategy {
+    pub allocation_ratio: u32,
+    pub max_allocation_bps: u32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoStrategySummary {
+    pub active_count: u32,
+    pub inactive_count: u32,
+    pub total_allocated_bps: u32,
+    pub idle_bps: u32,
+    pub weighted_risk_score: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoPerformance {
+    pub current_apy: u32,
+    pub average_apy: u32,
+    pub best_strategy: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoRisk {
+    pub risk_score: u32,
+    pub strategy_diversification: u32,
+    pub sharpe_ratio: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoAnalytics {
+    pub performance_metrics: DemoPerformance,
+    pub risk_metrics: DemoRisk,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemoPosition {
+    pub shares: u128,
+    pub deposited_amount: u128,
+    pub unclaimed_rewards: u128,
+}
+
+/// Everything the demo walkthrough prints, read from a live vault
+#[derive(Debug)]
+pub struct DemoData {
+    pub metrics: DemoMetrics,
+    pub share_price: u128,
+    pub strategies: Vec<(String, DemoStrategy)>,
+    pub strategy_summary: DemoStrategySummary,
+    pub analytics: DemoAnalytics,
+    pub position: Option<(String, DemoPosition)>,
+}
+
+/// Read the views behind the demo. `account` selects the sample user position.
+pub async fn gather_demo_data(view: &dyn VaultView, account: Option<&str>) -> CommandResult<DemoData> {
+    let metrics = view_as(view, "get_vault_metrics", json!({})).await?;
+    let share_price: U128 = view_as(view, "get_share_price", json!({})).await?;
+    let strategies = view_as(view, "get_all_strategies", json!({})).await?;
+    let strategy_summary = view_as(view, "get_strategy_summary", json!({})).await?;
+    let analytics = view_as(view, "get_analytics", json!({})).await?;
+
+    let position = match account {
+        Some(account_id) => {
+            let position: Option<DemoPosition> =
+                view_as(view, "get_user_position", json!({ "account_id": account_id })).await?;
+            position.map(|p| (account_id.to_string(), p))
+        }
+        None => None,
+    };
+
+    Ok(DemoData {
+        metrics,
+        share_price: share_price.0,
+        strategies,
+        strategy_summary,
+        analytics,
+        position,
+    })
+}
+
+fn print_separator() {
+    println!("\n{}", "-".repeat(50));
+}
+
+fn print_demo(contract_id: &str, data: &DemoData) {
+    println!("🏦 NEAR Protocol Yield Vault: {}", contract_id);
+    print_separator();
+
+    println!("📈 Investment Strategies");
+    for (name, strategy) in &data.strategies {
+        println!(
+            "- {} (Allocation: {}%, Max Allocation: {}%{})",
+            name,
+            strategy.allocation_ratio as f32 / 100.0,
+            strategy.max_allocation_bps as f32 / 100.0,
+            if strategy.is_active { "" } else { ", inactive" }
+        );
+    }
+
+    let summary = &data.strategy_summary;
+    println!("\nStrategy Summary:");
+    println!("- Active Strategies: {} ({} inactive)", summary.active_count, summary.inactive_count);
+    println!("- Allocated: {}% (Idle: {}%)", summary.total_allocated_bps as f32 / 100.0, summary.idle_bps as f32 / 100.0);
+    println!("- Weighted Risk Score: {}", summary.weighted_risk_score);
+    print_separator();
+
+    println!("💰 Vault Status");
+    println!("- Total TVL: {} NEAR", data.metrics.total_value_locked as f64 / YOCTO_NEAR);
+    println!("- Share Price: {} NEAR", data.share_price as f64 / YOCTO_NEAR);
+    println!("- APY: {}%", data.metrics.annual_percentage_yield as f32 / 100.0);
+    println!("- Total Profit: {} NEAR", data.metrics.total_profit as f64 / YOCTO_NEAR);
+    println!("- Total Users: {}", data.metrics.total_users);
+    print_separator();
+
+    println!("📊 Vault Analytics");
+    let performance = &data.analytics.performance_metrics;
+    println!("Performance Metrics:");
+    println!("- Current APY: {}%", performance.current_apy as f32 / 100.0);
+    println!("- Average APY: {}%", performance.average_apy as f32 / 100.0);
+    println!("- Best Strategy: {}", performance.best_strategy.clone().unwrap_or_default());
+
+    let risk = &data.analytics.risk_metrics;
+    println!("\nRisk Metrics:");
+    println!("- Risk Score: {}", risk.risk_score);
+    println!("- Strategy Diversification: {}%", risk.strategy_diversification as f32 / 100.0);
+    println!("- Sharpe Ratio: {:.2}", risk.sharpe_ratio);
+    print_separator();
+
+    if let Some((account_id, position)) = &data.position {
+        println!("👤 User Position: {}", account_id);
+        println!("- Shares: {}", position.shares);
+        println!("- Deposited Amount: {} NEAR", position.deposited_amount as f64 / YOCTO_NEAR);
+        println!("- Unclaimed Rewards: {} NEAR", position.unclaimed_rewards as f64 / YOCTO_NEAR);
+        print_separator();
+    }
+}
+
+/// Read-only walkthrough of a deployed vault, mirroring the scripted
+/// demonstration in the contract tests
+pub async fn demo(network: &str, contract_id: &str, account: Option<&str>) -> CommandResult<()> {
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .expect("Failed to set progress style")
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈"),
+    );
+    pb.set_message(format!("Reading vault {}...", contract_id));
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let view = RpcVaultView::new(network, contract_id);
+    let data = gather_demo_data(&view, account).await;
+    pb.finish_and_clear();
+
+    print_demo(contract_id, &data?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    // Canned contract responses keyed by view method
+    struct MockView {
+        responses: HashMap<&'static str, String>,
+        calls: Mutex<Vec<(String, Value)>>,
+    }
+
+    impl MockView {
+        fn new() -> Self {
+            let mut responses = HashMap::new();
+            responses.insert("get_vault_metrics", r#"{"total_value_locked":30000000000000000000000000,"annual_percentage_yield":1250,"total_users":2,"total_profit":1500000000000000000000000,"last_harvest_timestamp":0,"historical_apy":[],"risk_score":40,"sharpe_ratio":1.5}"#.to_string());
+            responses.insert("get_share_price", r#""1050000000000000000000000""#.to_string());
+            responses.insert("get_all_strategies", r#"[["defi_lending",{"name":"defi_lending","allocation_ratio":3000,"current_balance":0,"total_profit":0,"is_active":true,"last_harvest_timestamp":0,"risk_score":30,"max_allocation_bps":3000,"performance_history":[]}],["staking_pool",{"name":"staking_pool","allocation_ratio":0,"current_balance":0,"total_profit":0,"is_active":false,"last_harvest_timestamp":0,"risk_score":20,"max_allocation_bps":4000,"performance_history":[]}]]"#.to_string());
+            responses.insert("get_strategy_summary", r#"{"active_count":1,"inactive_count":1,"total_allocated_bps":3000,"idle_bps":7000,"weighted_risk_score":30}"#.to_string());
+            responses.insert("get_analytics", r#"{"tvl_history":[],"apy_history":[],"total_users":2,"total_profit":0,"risk_metrics":{"risk_score":40,"sharpe_ratio":1.25,"strategy_diversification":5000},"performance_metrics":{"current_apy":1250,"average_apy":1100,"best_strategy":"defi_lending","yield_stability":9000}}"#.to_string());
+            responses.insert("get_user_position", r#"{"shares":10000000000000000000000000,"deposited_amount":10000000000000000000000000,"last_deposit_timestamp":0,"unclaimed_rewards":0,"locked_until":0,"cumulative_rewards":0,"last_interaction":0}"#.to_string());
+            Self { responses, calls: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl VaultView for MockView {
+        async fn view(&self, method: &str, args: Value) -> CommandResult<Vec<u8>> {
+            self.calls.lock().unwrap().push((method.to_string(), args));
+            self.responses
+                .get(method)
+                .map(|body| body.clone().into_bytes())
+                .ok_or_else(|| CommandError::ApiError(format!("unexpected call {}", method)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gather_demo_data() {
+        let view = MockView::new();
+        let data = gather_demo_data(&view, Some("alice.near")).await.unwrap();
+
+        assert_eq!(data.metrics.total_value_locked, 30_000_000_000_000_000_000_000_000);
+        assert_eq!(data.metrics.annual_percentage_yield, 1250);
+        assert_eq!(data.share_price, 1_050_000_000_000_000_000_000_000);
+        assert_eq!(data.strategies.len(), 2);
+        assert!(!data.strategies[1].1.is_active);
+        assert_eq!(data.strategy_summary.total_allocated_bps, 3000);
+        assert_eq!(data.strategy_summary.idle_bps, 7000);
+        assert_eq!(data.analytics.performance_metrics.best_strategy.as_deref(), Some("defi_lending"));
+
+        let (account, position) = data.position.unwrap();
+        assert_eq!(account, "alice.near");
+        assert_eq!(position.shares, 10_000_000_000_000_000_000_000_000);
+
+        // Only view methods are touched
+        let calls = view.calls.lock().unwrap();
+        assert!(calls.iter().all(|(method, _)| method.starts_with("get_")));
+        assert_eq!(calls.last().unwrap().1, json!({ "account_id": "alice.near" }));
+    }
+
+    #[tokio::test]
+    async fn test_gather_demo_data_without_position() {
+        let mut view = MockView::new();
+        view.responses.insert("get_user_position", "null".to_string());
+
+        let data = gather_demo_data(&view, Some("nobody.near")).await.unwrap();
+        assert!(data.position.is_none());
+
+        let data = gather_demo_data(&view, None).await.unwrap();
+        assert!(data.position.is_none());
+    }
+
+    #[test]
+    fn test_decode_view_response() {
+        let body = br#""42""#;
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": "vault-cli",
+            "result": { "result": body.to_vec(), "logs": [], "block_height": 1 }
+        });
+        assert_eq!(decode_view_response("get_tvl", &response).unwrap(), body.to_vec());
+
+        let failed = json!({ "result": { "error": "wasm execution failed", "logs": [] } });
+        assert!(decode_view_response("get_tvl", &failed).is_err());
+
+        let rpc_error = json!({ "error": { "code": -32000, "message": "Server error" } });
+        assert!(decode_view_response("get_tvl", &rpc_error).is_err());
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b"{}"), "e30=");
+        assert_eq!(base64_encode(br#"{"account_id":"alice.near"}"#), "eyJhY2NvdW50X2lkIjoiYWxpY2UubmVhciJ9");
+        assert_eq!(base64_encode(b""), "");
+    }
+}
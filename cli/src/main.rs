@@ -60,6 +60,14 @@ enum VaultCommands {
         #[arg(long)]
         amount: String,
     },
+    /// Read-only walkthrough of a deployed vault
+    Demo {
+        #[arg(long)]
+        contract_id: String,
+        /// Account whose position to show
+        #[arg(long)]
+        account: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -122,26 +130,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 VaultCommands::Deposit { amount } => {
                     info!("Depositing {} into vault", amount);
                     let amount = parse_amount(amount)?;
-                    vault::deposit(U128(amount)).await?
+                    vault::deposit(U128(amount)).await
                         .map_err(|e| format!("Deposit failed: {}", e))?;
                 }
                 VaultCommands::Withdraw { amount } => {
                     info!("Withdrawing {} from vault", amount);
                     let amount = parse_amount(amount)?;
-                    vault::withdraw(U128(amount)).await?
+                    vault::withdraw(U128(amount)).await
                         .map_err(|e| format!("Withdrawal failed: {}", e))?;
                 }
+                VaultCommands::Demo { contract_id, account } => {
+                    info!("Running demo against {} on {}", contract_id, network);
+                    vault::demo(&network, contract_id, account.as_deref()).await?;
+                }
             }
         }
         Commands::Bridge { command } => {
             match command {
                 BridgeCommands::Transfer { amount, to_chain } => {
                     info!("Transferring {} to {}", amount, to_chain);
-                    bridge::transfer(amount.parse()?, to_chain).await?;
+                    bridge::bridge_tokens("near", to_chain, "near", U128(parse_amount(amount)?)).await?;
                 }
                 BridgeCommands::Status { tx_hash } => {
                     info!("Checking status of transfer {}", tx_hash);
-                    bridge::check_status(tx_hash).await?;
+                    bridge::get_bridge_status(tx_hash).await?;
                 }
             }
         }
@@ -153,7 +165,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 OracleCommands::Tvl { protocol } => {
                     info!("Getting TVL for {}", protocol);
-                    oracle::get_tvl(protocol).await?
+                    oracle::get_tvl(protocol).await
                         .map_err(|e| format!("Failed to get TVL: {}", e))?;
                 }
             }
@@ -162,7 +174,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             match command {
                 MonitorCommands::Analytics { type_ } => {
                     info!("Viewing {} analytics", type_);
-                    monitoring::view_events(type_).await?;
+                    match type_.as_str() {
+                        "health" => monitoring::get_system_health().await?,
+                        "transactions" => monitoring::get_transaction_metrics().await?,
+                        "alerts" => monitoring::get_alerts().await?,
+                        other => return Err(format!("Unknown analytics type: {}", other).into()),
+                    }
                 }
             }
         }
@@ -190,6 +207,7 @@ fn load_config(path: &PathBuf) -> Result<Value, Box<dyn std::error::Error>> {
 // Example usage:
 /*
 $ vault-cli --network testnet vault deposit 100
+$ vault-cli --network testnet vault demo --contract-id vault.testnet --account alice.testnet
 $ vault-cli bridge transfer 50 aurora
 $ vault-cli oracle price ETH
 $ vault-cli monitor health